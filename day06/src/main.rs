@@ -1,46 +1,104 @@
+mod matrix;
+mod population;
+
 use std::io::{BufRead, BufReader};
 use std::error::Error;
 use std::fs::File;
-use std::collections::VecDeque;
+use std::io::BufWriter;
 use memmap2::Mmap;
+#[cfg(test)]
+use num_bigint::BigUint;
+
+use matrix::{Exact, Modular};
+use population::{Population, Species};
 
 fn main() -> Result<(), Box<dyn Error>> {
+    // Process options
+    let mut input = "input06.txt".to_string();
+    let mut csv_file = None;
+    let mut days = 256;
+    let mut species = Vec::new();
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--input" => input = args.next().ok_or("--input expects a file name")?,
+            "--csv" => csv_file = Some(args.next().ok_or("--csv expects a file name")?),
+            "--days" => days = args.next().ok_or("--days expects a number of days")?.parse()?,
+            "--species" => species.push(parse_species(&args.next().ok_or("--species expects name:reset:newborn[:maturation[:offspring]]=timers")?)?),
+            _ => return Err(format!("Unrecognised argument '{}'", arg).into()),
+        }
+    }
+
     // Load the input file
-    let timers = load_input("input06.txt")?;
+    let timers = load_input(&input)?;
+    let mut population = Population::new(&timers)?;
 
     // Run parts
-    part1(&timers);
-    part2(&timers);
+    part1(&population);
+    part2(&population);
+    huge(&population)?;
+
+    // Add extra species
+    if !species.is_empty() {
+        for (species, timers) in species {
+            population.add_species(species, &timers)?;
+        }
+
+        let counts = population.species_counts_after(&Exact, days);
+
+        println!("Population after {} days: {}", days, counts.iter().map(|c| c.to_string()).collect::<Vec<_>>().join(", "));
+    }
+
+    // Write time series if requested
+    if let Some(csv_file) = csv_file {
+        let mut out = BufWriter::new(File::create(&csv_file)?);
+
+        population.write_csv(&mut out, days)?;
+
+        println!("Population time series written to {}", csv_file);
+    }
 
     Ok(())
 }
 
-fn part1(timers: &[u8]) {
-    let count = simulate_growth(timers, 80);
+fn part1(population: &Population) {
+    let count = population.count_after(&Exact, 80);
 
     println!("Part 1: Number of lantern fish after 80 days: {}", count);
 }
 
-fn part2(timers: &[u8]) {
-    let count = simulate_growth(timers, 256);
+fn part2(population: &Population) {
+    let count = population.count_after(&Exact, 256);
 
     println!("Part 2: Number of lantern fish after 256 days: {}", count);
 }
 
-fn simulate_growth(timers: &[u8], days: usize) -> u64 {
-    let mut counts: VecDeque<u64> = VecDeque::from([0; 9]);
+fn huge(population: &Population) -> Result<(), String> {
+    const DAYS: u64 = 1_000_000_000_000_000_000;
+    const MODULUS: u64 = 1_000_000_007;
 
-    for t in timers {
-        counts[*t as usize] += 1;
-    }
+    let count = population.count_after(&Modular::new(MODULUS)?, DAYS);
 
-    for _ in 0..days {
-        let spawners = counts.pop_front().unwrap();
-        counts[6] += spawners;
-        counts.push_back(spawners);
-    }
+    println!("Number of lantern fish after {} days mod {}: {}", DAYS, MODULUS, count);
+
+    Ok(())
+}
+
+fn parse_species(text: &str) -> Result<(Species, Vec<u8>), String> {
+    // Species definition followed by the initial timers
+    let (species, timers) = text.split_once('=').ok_or_else(|| format!("Species '{}' has no initial timers", text))?;
+
+    let timers = timers.split(',')
+        .map(|t| t.trim().parse::<u8>().map_err(|_| format!("Invalid timer '{}'", t)))
+        .collect::<Result<Vec<u8>, _>>()?;
+
+    Ok((Species::parse(species)?, timers))
+}
 
-    counts.iter().sum()
+#[cfg(test)]
+fn simulate_growth(timers: &[u8], days: u64) -> BigUint {
+    Population::new(timers).unwrap().count_after(&Exact, days)
 }
 
 fn load_input(file: &str) -> Result<Vec<u8>, Box<dyn Error>> {
@@ -86,11 +144,118 @@ fn test_growth() {
     let timers = vec![3,4,3,1,2];
 
     let count = simulate_growth(&timers, 18);
-    assert_eq!(26, count, "Count incorrect");
+    assert_eq!(BigUint::from(26u64), count, "Count incorrect");
 
     let count = simulate_growth(&timers, 80);
-    assert_eq!(5934, count, "Count incorrect");
+    assert_eq!(BigUint::from(5934u64), count, "Count incorrect");
 
     let count = simulate_growth(&timers, 256);
-    assert_eq!(26984457539, count, "Count incorrect");
+    assert_eq!(BigUint::from(26984457539u64), count, "Count incorrect");
+}
+
+#[test]
+fn test_growth_modular() {
+    let timers = vec![3,4,3,1,2];
+    let population = Population::new(&timers).unwrap();
+
+    for days in [0, 18, 80, 256, 1000] {
+        let exact = population.count_after(&Exact, days);
+        let modular = population.count_after(&Modular::new(1_000_000_007).unwrap(), days);
+
+        assert_eq!(exact % 1_000_000_007u64, BigUint::from(modular), "Modular count incorrect");
+    }
+}
+
+#[test]
+fn test_species() {
+    use population::Species;
+
+    let timers = vec![3,4,3,1,2];
+
+    // Newborns maturing for two days before starting on the reset timer behave the same
+    let mut population = Population::default();
+
+    population.add_species(Species {
+        name: "maturing".to_string(),
+        reset_timer: 6,
+        newborn_timer: 6,
+        maturation: 2,
+        offspring: 1,
+    }, &timers).unwrap();
+
+    assert_eq!(BigUint::from(5934u64), population.count_after(&Exact, 80), "Count incorrect");
+
+    // Species with two offspring per spawn
+    let twins = Species {
+        name: "twins".to_string(),
+        offspring: 2,
+        ..Species::lanternfish()
+    };
+
+    population.add_species(twins, &[0]).unwrap();
+
+    // Day 1: 1 reset + 2 newborns, day 8: 3 reset + 2 newborns
+    let series = population.series(&Exact, 8);
+
+    assert_eq!(series[0][1], BigUint::from(1u64));
+    assert_eq!(series[1][1], BigUint::from(3u64));
+    assert_eq!(series[7][1], BigUint::from(3u64));
+    assert_eq!(series[8][1], BigUint::from(5u64));
+
+    let counts = population.species_counts_after(&Exact, 80);
+
+    assert_eq!(counts[0], BigUint::from(5934u64));
+    assert_eq!(counts[1], population.series(&Exact, 80)[80][1]);
+
+    // Timers outside the species range are rejected
+    assert_eq!(population.add_species(Species::lanternfish(), &[9]), Err("Timer 9 out of range for lanternfish".to_string()));
+    assert!(Population::new(&[3, 12]).is_err());
+
+    // Species from the command line
+    let (species, timers) = parse_species("kraken:8:10:2:3=0,5").unwrap();
+
+    assert_eq!((species.name.as_str(), species.reset_timer, species.newborn_timer, species.maturation, species.offspring), ("kraken", 8, 10, 2, 3));
+    assert_eq!(timers, vec![0, 5]);
+
+    let (species, _) = parse_species("slow:9:11=1").unwrap();
+    assert_eq!((species.maturation, species.offspring), (0, 1));
+
+    assert!(parse_species("kraken:8:10").is_err());
+    assert!(parse_species("kraken:8=1").is_err());
+    assert!(parse_species("kraken:x:10=1").is_err());
+
+    // Limits on the matrix size and multipliers
+    assert_eq!(parse_species("x:4000000000:1=0").err(), Some("The reset timer of x is 4000000000, at most 255 is allowed".to_string()));
+    assert!(parse_species("x:1:1:99999999999999999999=0").is_err());
+
+    let overflow = Species { name: "x".to_string(), reset_timer: 1, newborn_timer: 1, maturation: 0, offspring: u64::MAX };
+    assert_eq!(population.add_species(overflow, &[0]), Err("Too many offspring for x".to_string()));
+
+    let mut population = Population::default();
+    let large = Species { name: "large".to_string(), reset_timer: 255, newborn_timer: 255, maturation: 255, offspring: 1 };
+
+    population.add_species(large.clone(), &[0]).unwrap();
+    population.add_species(large.clone(), &[0]).unwrap();
+    assert_eq!(population.add_species(large, &[0]), Err("Adding large makes more than 1024 timer states".to_string()));
+}
+
+#[test]
+fn test_csv() {
+    use population::Species;
+
+    let mut population = Population::new(&[3,4,3,1,2]).unwrap();
+
+    population.add_species(Species { name: "other".to_string(), ..Species::lanternfish() }, &[0]).unwrap();
+
+    let mut out = Vec::new();
+    population.write_csv(&mut out, 4).unwrap();
+
+    assert_eq!(String::from_utf8(out).unwrap(), "\
+day,lanternfish,other,total
+0,5,1,6
+1,5,2,7
+2,6,2,8
+3,7,2,9
+4,9,2,11
+");
 }
@@ -0,0 +1,170 @@
+use num_bigint::BigUint;
+
+pub trait Arithmetic {
+    type Value: Clone;
+
+    fn zero(&self) -> Self::Value;
+    fn value(&self, value: u64) -> Self::Value;
+    fn add(&self, a: &Self::Value, b: &Self::Value) -> Self::Value;
+    fn mul(&self, a: &Self::Value, b: &Self::Value) -> Self::Value;
+}
+
+// Exact arithmetic using arbitrary precision integers
+pub struct Exact;
+
+impl Arithmetic for Exact {
+    type Value = BigUint;
+
+    fn zero(&self) -> BigUint {
+        BigUint::default()
+    }
+
+    fn value(&self, value: u64) -> BigUint {
+        BigUint::from(value)
+    }
+
+    fn add(&self, a: &BigUint, b: &BigUint) -> BigUint {
+        a + b
+    }
+
+    fn mul(&self, a: &BigUint, b: &BigUint) -> BigUint {
+        a * b
+    }
+}
+
+// Arithmetic modulo a given value
+pub struct Modular {
+    modulus: u64,
+}
+
+impl Modular {
+
+    pub fn new(modulus: u64) -> Result<Self, String> {
+        if modulus == 0 {
+            return Err("Modulus must be at least 1".to_string());
+        }
+
+        Ok(Modular { modulus })
+    }
+
+}
+
+impl Arithmetic for Modular {
+    type Value = u64;
+
+    fn zero(&self) -> u64 {
+        0
+    }
+
+    fn value(&self, value: u64) -> u64 {
+        value % self.modulus
+    }
+
+    fn add(&self, a: &u64, b: &u64) -> u64 {
+        ((*a as u128 + *b as u128) % self.modulus as u128) as u64
+    }
+
+    fn mul(&self, a: &u64, b: &u64) -> u64 {
+        ((*a as u128 * *b as u128) % self.modulus as u128) as u64
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Matrix<T> {
+    size: usize,
+    elems: Vec<T>,
+}
+
+impl<T: Clone> Matrix<T> {
+
+    pub fn new<A: Arithmetic<Value = T>>(arith: &A, size: usize) -> Self {
+        Matrix { size, elems: vec![arith.zero(); size * size] }
+    }
+
+    pub fn identity<A: Arithmetic<Value = T>>(arith: &A, size: usize) -> Self {
+        let mut matrix = Self::new(arith, size);
+
+        for i in 0..size {
+            matrix.set(i, i, arith.value(1));
+        }
+
+        matrix
+    }
+
+    pub fn from_u64<A: Arithmetic<Value = T>>(arith: &A, size: usize, elems: &[u64]) -> Self {
+        assert_eq!(elems.len(), size * size, "Matrix element count incorrect");
+
+        Matrix { size, elems: elems.iter().map(|&e| arith.value(e)).collect() }
+    }
+
+    pub fn get(&self, row: usize, col: usize) -> &T {
+        &self.elems[(row * self.size) + col]
+    }
+
+    pub fn set(&mut self, row: usize, col: usize, value: T) {
+        self.elems[(row * self.size) + col] = value;
+    }
+
+    pub fn mul<A: Arithmetic<Value = T>>(&self, arith: &A, other: &Self) -> Self {
+        let mut result = Self::new(arith, self.size);
+
+        for row in 0..self.size {
+            for col in 0..self.size {
+                let mut sum = arith.zero();
+
+                for k in 0..self.size {
+                    sum = arith.add(&sum, &arith.mul(self.get(row, k), other.get(k, col)));
+                }
+
+                result.set(row, col, sum);
+            }
+        }
+
+        result
+    }
+
+    pub fn pow<A: Arithmetic<Value = T>>(&self, arith: &A, mut exp: u64) -> Self {
+        // Raise to a power by repeated squaring
+        let mut result = Self::identity(arith, self.size);
+        let mut square = self.clone();
+
+        while exp > 0 {
+            if exp & 1 == 1 {
+                result = result.mul(arith, &square);
+            }
+
+            exp >>= 1;
+
+            if exp > 0 {
+                square = square.mul(arith, &square);
+            }
+        }
+
+        result
+    }
+
+    pub fn mul_vec<A: Arithmetic<Value = T>>(&self, arith: &A, vec: &[T]) -> Vec<T> {
+        (0..self.size).map(|row| {
+            (0..self.size).fold(arith.zero(), |sum, col| {
+                arith.add(&sum, &arith.mul(self.get(row, col), &vec[col]))
+            })
+        }).collect()
+    }
+
+}
+
+#[test]
+fn test_pow() {
+    let fib = Matrix::from_u64(&Exact, 2, &[1, 1, 1, 0]);
+
+    assert_eq!(*fib.pow(&Exact, 0).get(0, 1), BigUint::from(0u64));
+    assert_eq!(*fib.pow(&Exact, 10).get(0, 1), BigUint::from(55u64));
+    assert_eq!(*fib.pow(&Exact, 100).get(0, 1), "354224848179261915075".parse().unwrap());
+
+    let arith = Modular::new(1_000_000_007).unwrap();
+    let fib = Matrix::from_u64(&arith, 2, &[1, 1, 1, 0]);
+
+    assert_eq!(*fib.pow(&arith, 100).get(0, 1), 687995182);
+
+    assert!(Modular::new(0).is_err());
+}
@@ -0,0 +1,249 @@
+use std::io::{self, Write};
+
+use super::matrix::{Arithmetic, Exact, Matrix};
+
+// Limits on the timers and maturation days, and the total states across all species, to keep
+// the transition matrix a sensible size
+pub const MAX_TIMER: usize = 255;
+pub const MAX_STATES: usize = 1024;
+
+#[derive(Debug, Clone)]
+pub struct Species {
+    pub name: String,
+    pub reset_timer: usize,
+    pub newborn_timer: usize,
+    pub maturation: usize,
+    pub offspring: u64,
+}
+
+impl Species {
+
+    pub fn lanternfish() -> Self {
+        Species {
+            name: "lanternfish".to_string(),
+            reset_timer: 6,
+            newborn_timer: 8,
+            maturation: 0,
+            offspring: 1,
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        // name:reset:newborn[:maturation[:offspring]]
+        let fields: Vec<&str> = text.split(':').map(str::trim).collect();
+
+        if fields.len() < 3 || fields.len() > 5 || fields[0].is_empty() {
+            return Err(format!("Invalid species '{}', expected name:reset:newborn[:maturation[:offspring]]", text));
+        }
+
+        let number = |i: usize, default: u64| -> Result<u64, String> {
+            fields.get(i).map_or(Ok(default), |f| f.parse().map_err(|_| format!("Invalid number '{}' in species '{}'", f, text)))
+        };
+
+        let timer = |i: usize| -> Result<usize, String> {
+            // Out of range values are rejected by check
+            Ok(number(i, 0)?.try_into().unwrap_or(usize::MAX))
+        };
+
+        let species = Species {
+            name: fields[0].to_string(),
+            reset_timer: timer(1)?,
+            newborn_timer: timer(2)?,
+            maturation: timer(3)?,
+            offspring: number(4, 1)?,
+        };
+
+        species.check()?;
+
+        Ok(species)
+    }
+
+    fn check(&self) -> Result<(), String> {
+        for (what, value) in [("reset timer", self.reset_timer), ("newborn timer", self.newborn_timer), ("maturation", self.maturation)] {
+            if value > MAX_TIMER {
+                return Err(format!("The {} of {} is {}, at most {} is allowed", what, self.name, value, MAX_TIMER));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn max_timer(&self) -> usize {
+        std::cmp::max(self.reset_timer, self.newborn_timer)
+    }
+
+    fn states(&self) -> usize {
+        // Timer states followed by juvenile (maturing) states
+        self.max_timer() + 1 + self.maturation
+    }
+
+    fn transitions(&self) -> Result<Vec<(usize, usize, u64)>, String> {
+        // Returns (to state, from state, multiplier) with moves between the same states merged
+        let mut moves = Vec::new();
+
+        // Each timer counts down by one
+        for t in 1..=self.max_timer() {
+            moves.push((t - 1, t, 1));
+        }
+
+        // Spawners reset
+        moves.push((self.reset_timer, 0, 1));
+
+        // Spawners produce offspring, either maturing first or starting on the newborn timer
+        let juvenile = self.max_timer() + 1;
+
+        if self.maturation > 0 {
+            moves.push((juvenile + self.maturation - 1, 0, self.offspring));
+
+            for j in 1..self.maturation {
+                moves.push((juvenile + j - 1, juvenile + j, 1));
+            }
+
+            moves.push((self.newborn_timer, juvenile, 1));
+        } else {
+            moves.push((self.newborn_timer, 0, self.offspring));
+        }
+
+        let mut transitions: Vec<(usize, usize, u64)> = Vec::new();
+
+        for (to, from, mult) in moves {
+            match transitions.iter_mut().find(|(t, f, _)| (*t, *f) == (to, from)) {
+                Some((_, _, total)) => *total = total.checked_add(mult).ok_or_else(|| format!("Too many offspring for {}", self.name))?,
+                None => transitions.push((to, from, mult)),
+            }
+        }
+
+        Ok(transitions)
+    }
+
+}
+
+#[derive(Default)]
+pub struct Population {
+    species: Vec<Species>,
+    counts: Vec<Vec<u64>>,
+    transitions: Vec<Vec<(usize, usize, u64)>>,
+}
+
+impl Population {
+
+    pub fn new(timers: &[u8]) -> Result<Self, String> {
+        let mut population = Population::default();
+
+        population.add_species(Species::lanternfish(), timers)?;
+
+        Ok(population)
+    }
+
+    pub fn add_species(&mut self, species: Species, timers: &[u8]) -> Result<(), String> {
+        species.check()?;
+
+        if self.species.iter().chain([&species]).map(|s| s.states()).sum::<usize>() > MAX_STATES {
+            return Err(format!("Adding {} makes more than {} timer states", species.name, MAX_STATES));
+        }
+
+        let transitions = species.transitions()?;
+        let mut counts = vec![0; species.states()];
+
+        for t in timers {
+            if *t as usize > species.max_timer() {
+                return Err(format!("Timer {} out of range for {}", t, species.name));
+            }
+
+            counts[*t as usize] += 1;
+        }
+
+        self.species.push(species);
+        self.counts.push(counts);
+        self.transitions.push(transitions);
+
+        Ok(())
+    }
+
+    pub fn count_after<A: Arithmetic>(&self, arith: &A, days: u64) -> A::Value {
+        Self::sum(arith, &self.species_counts_after(arith, days))
+    }
+
+    pub fn species_counts_after<A: Arithmetic>(&self, arith: &A, days: u64) -> Vec<A::Value> {
+        // Raise the transition matrix to the number of days
+        let matrix = self.transition_matrix(arith).pow(arith, days);
+
+        // Apply to the initial counts
+        let counts = matrix.mul_vec(arith, &self.initial(arith));
+
+        self.species_totals(arith, &counts)
+    }
+
+    pub fn series<A: Arithmetic>(&self, arith: &A, days: u64) -> Vec<Vec<A::Value>> {
+        let matrix = self.transition_matrix(arith);
+
+        let mut counts = self.initial(arith);
+        let mut series = Vec::with_capacity(days as usize + 1);
+
+        series.push(self.species_totals(arith, &counts));
+
+        // Step the simulation one day at a time
+        for _ in 0..days {
+            counts = matrix.mul_vec(arith, &counts);
+            series.push(self.species_totals(arith, &counts));
+        }
+
+        series
+    }
+
+    pub fn write_csv<W: Write>(&self, out: &mut W, days: u64) -> io::Result<()> {
+        // Write header
+        let names: Vec<&str> = self.species.iter().map(|s| s.name.as_str()).collect();
+
+        writeln!(out, "day,{},total", names.join(","))?;
+
+        // Write a line per day
+        for (day, totals) in self.series(&Exact, days).iter().enumerate() {
+            let cols: Vec<String> = totals.iter().map(|t| t.to_string()).collect();
+
+            writeln!(out, "{},{},{}", day, cols.join(","), Self::sum(&Exact, totals))?;
+        }
+
+        Ok(())
+    }
+
+    fn initial<A: Arithmetic>(&self, arith: &A) -> Vec<A::Value> {
+        self.counts.iter().flatten().map(|&c| arith.value(c)).collect()
+    }
+
+    fn species_totals<A: Arithmetic>(&self, arith: &A, counts: &[A::Value]) -> Vec<A::Value> {
+        let mut offset = 0;
+
+        self.species.iter().map(|species| {
+            let states = species.states();
+            let total = Self::sum(arith, &counts[offset..offset + states]);
+
+            offset += states;
+
+            total
+        }).collect()
+    }
+
+    fn sum<A: Arithmetic>(arith: &A, values: &[A::Value]) -> A::Value {
+        values.iter().fold(arith.zero(), |sum, v| arith.add(&sum, v))
+    }
+
+    fn transition_matrix<A: Arithmetic>(&self, arith: &A) -> Matrix<A::Value> {
+        let size = self.species.iter().map(|s| s.states()).sum();
+        let mut elems = vec![0; size * size];
+
+        // Build block diagonal matrix with a block per species
+        let mut offset = 0;
+
+        for (species, transitions) in self.species.iter().zip(&self.transitions) {
+            for &(to, from, mult) in transitions {
+                elems[((offset + to) * size) + offset + from] = mult;
+            }
+
+            offset += species.states();
+        }
+
+        Matrix::from_u64(arith, size, &elems)
+    }
+
+}
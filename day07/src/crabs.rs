@@ -0,0 +1,291 @@
+use super::fuel::{Fuel, FuelCost, Shape};
+
+pub type Pos = u32;
+pub type Weight = u64;
+
+pub struct Crabs {
+    // Distinct crab positions in ascending order
+    positions: Vec<Pos>,
+    // Total crab weight at each position
+    weights: Vec<i128>,
+    // Prefix sums of weight, weighted position and weighted position squared
+    weight_sum: Vec<i128>,
+    pos_sum: Vec<i128>,
+    sq_sum: Vec<i128>,
+}
+
+impl Crabs {
+
+    pub fn new(positions: &[Pos]) -> Result<Self, String> {
+        Self::weighted(&positions.iter().map(|&p| (p, 1)).collect::<Vec<_>>())
+    }
+
+    pub fn weighted(crabs: &[(Pos, Weight)]) -> Result<Self, String> {
+        if crabs.is_empty() {
+            return Err("No crab positions".to_string());
+        }
+
+        let mut sorted = crabs.to_vec();
+        sorted.sort_unstable();
+
+        // Sum crab weights at each distinct position
+        let mut distinct: Vec<(Pos, i128)> = Vec::new();
+
+        for (p, weight) in sorted {
+            match distinct.last_mut() {
+                Some((last, total)) if *last == p => *total += weight as i128,
+                _ => distinct.push((p, weight as i128)),
+            }
+        }
+
+        // Every term in the distance sums is at most the total weight times the largest
+        // position squared, and there are fewer than eight of them
+        let total_weight: i128 = distinct.iter().map(|&(_, weight)| weight).sum();
+        let max_pos = distinct.last().unwrap().0 as i128;
+
+        max_pos.checked_mul(max_pos).and_then(|sq| sq.checked_mul(total_weight)).and_then(|bound| bound.checked_mul(8))
+            .ok_or("Crab weights and positions too large")?;
+
+        // Build prefix sums
+        let mut weight_sum = vec![0];
+        let mut pos_sum = vec![0];
+        let mut sq_sum = vec![0];
+
+        for &(p, weight) in &distinct {
+            let p = p as i128;
+
+            weight_sum.push(weight_sum.last().unwrap() + weight);
+            pos_sum.push(pos_sum.last().unwrap() + (weight * p));
+            sq_sum.push(sq_sum.last().unwrap() + (weight * p * p));
+        }
+
+        let (positions, weights) = distinct.into_iter().unzip();
+
+        Ok(Crabs {
+            positions,
+            weights,
+            weight_sum,
+            pos_sum,
+            sq_sum,
+        })
+    }
+
+    pub fn min_pos(&self) -> Pos {
+        self.positions[0]
+    }
+
+    pub fn max_pos(&self) -> Pos {
+        *self.positions.last().unwrap()
+    }
+
+    pub fn optimum(&self, cost: &dyn FuelCost) -> (Pos, Fuel) {
+        match cost.shape() {
+            Shape::Linear => self.median_optimum(cost),
+            Shape::Convex => self.convex_optimum(cost),
+            Shape::Other => self.scan_optimum(cost),
+        }
+    }
+
+    pub fn cost_curve<'a>(&'a self, cost: &'a dyn FuelCost) -> impl Iterator<Item = (Pos, Fuel)> + 'a {
+        let mut below = 0;
+
+        (self.min_pos()..=self.max_pos()).map(move |x| {
+            // Keep track of the number of positions at or below x
+            while below < self.positions.len() && self.positions[below] <= x {
+                below += 1;
+            }
+
+            (x, self.fuel_below(cost, below, x))
+        })
+    }
+
+    fn fuel_below(&self, cost: &dyn FuelCost, below: usize, x: Pos) -> Fuel {
+        // Calculates the fuel used given the number of distinct positions at or below x
+        let (dist, dist_sq) = self.distances(below, x);
+
+        match cost.total(dist, dist_sq) {
+            Some(total) => total as Fuel,
+            None => self.positions.iter().zip(&self.weights).map(|(&p, &weight)| {
+                weight as Fuel * cost.fuel(p.abs_diff(x) as u64)
+            }).sum()
+        }
+    }
+
+    fn distances(&self, below: usize, x: Pos) -> (i128, i128) {
+        // Returns the sum of weighted distances and weighted squared distances from
+        // all crabs to position x, given the number of distinct positions at or below x
+        let x = x as i128;
+
+        let (lweight, lsum, lsq) = (self.weight_sum[below], self.pos_sum[below], self.sq_sum[below]);
+        let rweight = self.weight_sum.last().unwrap() - lweight;
+        let rsum = self.pos_sum.last().unwrap() - lsum;
+        let rsq = self.sq_sum.last().unwrap() - lsq;
+
+        let dist = (x * lweight) - lsum + rsum - (x * rweight);
+        let dist_sq = (x * x * lweight) - (2 * x * lsum) + lsq + rsq - (2 * x * rsum) + (x * x * rweight);
+
+        (dist, dist_sq)
+    }
+
+    fn median_optimum(&self, cost: &dyn FuelCost) -> (Pos, Fuel) {
+        // Find the lower weighted median position
+        let total = *self.weight_sum.last().unwrap();
+        let split = self.weight_sum.partition_point(|&w| 2 * w < total).max(1);
+        let pos = self.positions[split - 1];
+
+        (pos, self.fuel_below(cost, split, pos))
+    }
+
+    fn convex_optimum(&self, cost: &dyn FuelCost) -> (Pos, Fuel) {
+        let count = self.positions.len();
+
+        // Binary search for the best crab position
+        let at_crab = |i: usize| self.fuel_below(cost, i + 1, self.positions[i]);
+        let best = first_non_decreasing(0, count - 1, at_crab);
+
+        let mut result = (self.positions[best], at_crab(best));
+
+        // Search the gaps either side of the best crab position. Within each gap
+        // the number of crabs either side is fixed
+        for gap in [best.checked_sub(1), Some(best)].into_iter().flatten() {
+            if gap + 1 >= count {
+                continue;
+            }
+
+            let lo = self.positions[gap] as usize;
+            let hi = self.positions[gap + 1] as usize;
+
+            let in_gap = |x: usize| self.fuel_below(cost, gap + 1, x as Pos);
+            let x = first_non_decreasing(lo, hi, in_gap);
+            let fuel = in_gap(x);
+
+            if fuel < result.1 || (fuel == result.1 && (x as Pos) < result.0) {
+                result = (x as Pos, fuel);
+            }
+        }
+
+        result
+    }
+
+    fn scan_optimum(&self, cost: &dyn FuelCost) -> (Pos, Fuel) {
+        // Try every position between the outermost crabs
+        self.cost_curve(cost).min_by_key(|&(x, fuel)| (fuel, x)).unwrap()
+    }
+
+}
+
+fn first_non_decreasing<F>(mut lo: usize, mut hi: usize, f: F) -> usize
+    where F: Fn(usize) -> Fuel
+{
+    // Finds the first x in lo..=hi where f(x + 1) >= f(x) for a convex function
+    while lo < hi {
+        let mid = lo + ((hi - lo) / 2);
+
+        if f(mid + 1) >= f(mid) {
+            hi = mid;
+        } else {
+            lo = mid + 1;
+        }
+    }
+
+    lo
+}
+
+#[cfg(test)]
+impl Crabs {
+
+    fn fuel_at(&self, cost: &dyn FuelCost, x: Pos) -> Fuel {
+        let below = self.positions.partition_point(|&p| p <= x);
+
+        self.fuel_below(cost, below, x)
+    }
+
+}
+
+#[cfg(test)]
+fn scan(crabs: &Crabs, cost: &dyn FuelCost) -> (Pos, Fuel) {
+    (crabs.min_pos()..=crabs.max_pos()).map(|x| {
+        let fuel = crabs.positions.iter().zip(&crabs.weights).map(|(&p, &weight)| {
+            weight as Fuel * cost.fuel(p.abs_diff(x) as u64)
+        }).sum();
+
+        (x, fuel)
+    }).min_by_key(|&(x, fuel)| (fuel, x)).unwrap()
+}
+
+#[test]
+fn test_against_scan() {
+    use super::fuel::{Capped, Linear, Quadratic, Triangular};
+
+    // Generate pseudo random positions
+    let mut seed: u64 = 12345;
+    let mut next = |max: u64| {
+        seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+        (seed >> 33) % max
+    };
+
+    for _ in 0..50 {
+        let len = next(40) as usize + 1;
+        let positions: Vec<Pos> = (0..len).map(|_| next(200) as Pos).collect();
+        let weighted: Vec<(Pos, Weight)> = positions.iter().map(|&p| (p, next(10))).collect();
+
+        for crabs in [Crabs::new(&positions).unwrap(), Crabs::weighted(&weighted).unwrap()] {
+            assert_eq!(crabs.optimum(&Linear).1, scan(&crabs, &Linear).1);
+            assert_eq!(crabs.optimum(&Triangular), scan(&crabs, &Triangular));
+            assert_eq!(crabs.optimum(&Quadratic), scan(&crabs, &Quadratic));
+
+            let capped = Capped { cost: Triangular, cap: 500 };
+            assert_eq!(crabs.optimum(&capped), scan(&crabs, &capped));
+
+            let cubic = |d: u64| (d * d * d) as Fuel;
+            assert_eq!(crabs.optimum(&cubic), scan(&crabs, &cubic));
+        }
+    }
+}
+
+#[test]
+fn test_cost_curve() {
+    use super::fuel::Triangular;
+
+    let crabs = Crabs::new(&[16,1,2,0,4,2,7,1,2,14]).unwrap();
+    let curve: Vec<(Pos, Fuel)> = crabs.cost_curve(&Triangular).collect();
+
+    assert_eq!(curve.len(), 17);
+    assert_eq!(curve[2], (2, 206));
+    assert_eq!(curve[5], (5, 168));
+
+    for &(x, fuel) in &curve {
+        assert_eq!(fuel, crabs.fuel_at(&Triangular, x));
+    }
+}
+
+#[test]
+fn test_large() {
+    use super::fuel::{Linear, Triangular};
+
+    let positions: Vec<Pos> = (0..2_000_000u64).map(|i| ((i * 2654435761) % 4_000_000_000) as Pos).collect();
+    let crabs = Crabs::new(&positions).unwrap();
+
+    for cost in [&Linear as &dyn FuelCost, &Triangular] {
+        let (pos, fuel) = crabs.optimum(cost);
+
+        assert_eq!(fuel, crabs.fuel_at(cost, pos));
+        assert!(fuel <= crabs.fuel_at(cost, pos + 1) && fuel <= crabs.fuel_at(cost, pos - 1));
+    }
+}
+
+#[test]
+fn test_limits() {
+    use super::fuel::Linear;
+
+    assert_eq!(Crabs::new(&[]).err(), Some("No crab positions".to_string()));
+
+    // Heavy crabs far apart overflow the distance sums
+    assert!(Crabs::weighted(&[(0, Weight::MAX), (Pos::MAX, Weight::MAX)]).is_err());
+
+    let crabs = Crabs::weighted(&[(0, 1_000_000), (Pos::MAX, 1_000_000)]).unwrap();
+    assert_eq!(crabs.optimum(&Linear), (0, 1_000_000 * Pos::MAX as Fuel));
+
+    // The curve is produced lazily
+    assert_eq!(crabs.cost_curve(&Linear).nth(1), Some((1, 1_000_000 + 1_000_000 * (Pos::MAX as Fuel - 1))));
+}
@@ -0,0 +1,129 @@
+pub type Fuel = u128;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shape {
+    // Cost proportional to distance, optimum is the median
+    Linear,
+    // Total cost is convex in position
+    Convex,
+    // No known shape, requires a full scan
+    Other,
+}
+
+pub trait FuelCost {
+    // Fuel used by a single crab moving a distance
+    fn fuel(&self, dist: u64) -> Fuel;
+
+    fn shape(&self) -> Shape {
+        Shape::Other
+    }
+
+    // Total fuel given the sum of distances and the sum of squared distances,
+    // if it can be calculated from those alone
+    fn total(&self, _dist: i128, _dist_sq: i128) -> Option<i128> {
+        None
+    }
+}
+
+pub struct Linear;
+
+impl FuelCost for Linear {
+    fn fuel(&self, dist: u64) -> Fuel {
+        dist as Fuel
+    }
+
+    fn shape(&self) -> Shape {
+        Shape::Linear
+    }
+
+    fn total(&self, dist: i128, _dist_sq: i128) -> Option<i128> {
+        Some(dist)
+    }
+}
+
+pub struct Triangular;
+
+impl FuelCost for Triangular {
+    fn fuel(&self, dist: u64) -> Fuel {
+        let dist = dist as Fuel;
+
+        (dist * (dist + 1)) / 2
+    }
+
+    fn shape(&self) -> Shape {
+        Shape::Convex
+    }
+
+    fn total(&self, dist: i128, dist_sq: i128) -> Option<i128> {
+        Some((dist_sq + dist) / 2)
+    }
+}
+
+pub struct Quadratic;
+
+impl FuelCost for Quadratic {
+    fn fuel(&self, dist: u64) -> Fuel {
+        let dist = dist as Fuel;
+
+        dist * dist
+    }
+
+    fn shape(&self) -> Shape {
+        Shape::Convex
+    }
+
+    fn total(&self, _dist: i128, dist_sq: i128) -> Option<i128> {
+        Some(dist_sq)
+    }
+}
+
+// Limits the fuel used by each crab to a maximum
+pub struct Capped<C: FuelCost> {
+    pub cost: C,
+    pub cap: Fuel,
+}
+
+impl<C: FuelCost> FuelCost for Capped<C> {
+    fn fuel(&self, dist: u64) -> Fuel {
+        std::cmp::min(self.cost.fuel(dist), self.cap)
+    }
+}
+
+impl FuelCost for Box<dyn FuelCost> {
+    fn fuel(&self, dist: u64) -> Fuel {
+        self.as_ref().fuel(dist)
+    }
+
+    fn shape(&self) -> Shape {
+        self.as_ref().shape()
+    }
+
+    fn total(&self, dist: i128, dist_sq: i128) -> Option<i128> {
+        self.as_ref().total(dist, dist_sq)
+    }
+}
+
+pub fn parse_cost(spec: &str) -> Result<Box<dyn FuelCost>, String> {
+    // Parses linear, triangular, quadratic or capped:<cap>:<cost>
+    match spec.split_once(':') {
+        Some(("capped", rest)) => {
+            let (cap, cost) = rest.split_once(':').ok_or("capped cost expects capped:<cap>:<cost>")?;
+            let cap = cap.parse().map_err(|_| format!("Invalid fuel cap '{}'", cap))?;
+
+            Ok(Box::new(Capped { cost: parse_cost(cost)?, cap }))
+        }
+        _ => match spec {
+            "linear" => Ok(Box::new(Linear)),
+            "triangular" => Ok(Box::new(Triangular)),
+            "quadratic" => Ok(Box::new(Quadratic)),
+            _ => Err(format!("Unknown fuel cost '{}'", spec)),
+        }
+    }
+}
+
+// Any function of distance can be used as a cost
+impl<F: Fn(u64) -> Fuel> FuelCost for F {
+    fn fuel(&self, dist: u64) -> Fuel {
+        self(dist)
+    }
+}
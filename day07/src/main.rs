@@ -1,102 +1,98 @@
+mod crabs;
+mod fuel;
+
 use std::io::{BufRead, BufReader};
 use std::error::Error;
 use std::fs::File;
-use std::collections::hash_map::DefaultHasher;
-use std::hash::{Hash, Hasher};
+use std::io::{BufWriter, Write};
 use memmap2::Mmap;
-use cached::{cached_key, SizedCache};
+
+use crabs::{Crabs, Pos};
+use fuel::{Fuel, FuelCost, Linear, Triangular};
 
 fn main() -> Result<(), Box<dyn Error>> {
+    // Process options
+    let mut cost_spec = None;
+    let mut curve_file = None;
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--cost" => cost_spec = Some(args.next().ok_or("--cost expects a cost")?),
+            "--curve" => curve_file = Some(args.next().ok_or("--curve expects a file name")?),
+            _ => return Err(format!("Unrecognised argument '{}'", arg).into()),
+        }
+    }
+
+    // Check a custom cost before doing any work
+    let custom_cost = if cost_spec.is_some() || curve_file.is_some() {
+        let spec = cost_spec.unwrap_or_else(|| "triangular".to_string());
+        let cost = fuel::parse_cost(&spec)?;
+
+        Some((spec, cost))
+    } else {
+        None
+    };
+
     // Load the input file
     let positions = load_input("input07.txt")?;
 
+    let crabs = Crabs::new(&positions)?;
+
     // Run parts
-    part1(&positions);
-    part2(&positions);
+    part1(&crabs);
+    part2(&crabs);
+
+    // Solve for a custom cost if requested
+    if let Some((spec, cost)) = custom_cost {
+        custom(&crabs, &spec, cost.as_ref(), curve_file.as_deref())?;
+    }
 
     Ok(())
 }
 
-fn part1(positions: &[u16]) {
-    let (pos, fuel) = calculate_hpos1(positions);
+fn part1(crabs: &Crabs) {
+    let (pos, fuel) = calculate_hpos1(crabs);
 
     println!("Part 1: Optimum position {}, fuel used {}", pos, fuel);
 }
 
-fn part2(positions: &[u16]) {
-    let (pos, fuel) = calculate_hpos2(positions);
+fn part2(crabs: &Crabs) {
+    let (pos, fuel) = calculate_hpos2(crabs);
 
     println!("Part 2: Optimum position {}, fuel used {}", pos, fuel);
 }
 
-fn calculate_hpos1(positions: &[u16]) -> (u16, u32) {
-    calculate_hpos(positions, calculate_fuel1)
-}
-
-cached_key!{
-    DIST1: SizedCache<String, u32> = SizedCache::with_size(10);
-    Key = { format!("{}{}", pos_hash, from) };
-
-    fn calculate_fuel1(positions: &[u16], pos_hash: u64, from: u16) -> u32 = {
-        positions.iter().map(|p| (from as i32 - *p as i32).unsigned_abs()).sum::<u32>()
-    }
-}
-
-fn calculate_hpos2(positions: &[u16]) -> (u16, u32) {
-   calculate_hpos(positions, calculate_fuel2)
-}
-
-cached_key!{
-    DIST2: SizedCache<String, u32> = SizedCache::with_size(10);
-    Key = { format!("{}{}", pos_hash, from) };
-
-    fn calculate_fuel2(positions: &[u16], pos_hash: u64, from: u16) -> u32 = {
-        positions.iter().map(|p| {
-            let dist = (from as i32 - *p as i32).unsigned_abs();
-            (dist * (dist + 1)) / 2
-        }).sum::<u32>()
-    }
+fn calculate_hpos1(crabs: &Crabs) -> (Pos, Fuel) {
+    crabs.optimum(&Linear)
 }
 
-fn array_hash(positions: &[u16]) -> u64 {
-    let mut hasher = DefaultHasher::new();
-    positions.hash(&mut hasher);
-    hasher.finish()
+fn calculate_hpos2(crabs: &Crabs) -> (Pos, Fuel) {
+    crabs.optimum(&Triangular)
 }
 
-fn calculate_hpos(positions: &[u16], calc_fn: fn(&[u16], u64, u16) -> u32) -> (u16, u32) {
-    // Calculate the integer mean position
-    let sum: u32 = positions.iter().map(|p| *p as u32).sum();
-
-    let mean = sum / positions.len() as u32;
+fn custom(crabs: &Crabs, spec: &str, cost: &dyn FuelCost, curve_file: Option<&str>) -> Result<(), Box<dyn Error>> {
+    let (pos, fuel) = crabs.optimum(cost);
 
-    // Calculate the array hash
-    let pos_hash = array_hash(positions);
+    println!("Cost {}: Optimum position {}, fuel used {}", spec, pos, fuel);
 
-    // Calculate the optimum position
-    let mut test_pos = mean as u16;
+    // Write the cost curve
+    if let Some(file) = curve_file {
+        let mut out = BufWriter::new(File::create(file)?);
 
-    let mut test_fuel = calc_fn(positions, pos_hash, test_pos);
+        writeln!(out, "pos,fuel")?;
 
-    loop {
-        let test_down = calc_fn(positions, pos_hash, test_pos - 1);
-        let test_up = calc_fn(positions, pos_hash, test_pos + 1);
-
-        if test_down < test_fuel {
-            test_pos -= 1;
-            test_fuel = test_down;
-        } else if test_up < test_fuel {
-            test_pos += 1;
-            test_fuel = test_up;
-        } else {
-            break
+        for (pos, fuel) in crabs.cost_curve(cost) {
+            writeln!(out, "{},{}", pos, fuel)?;
         }
+
+        println!("Cost curve written to {}", file);
     }
 
-    (test_pos, test_fuel)
+    Ok(())
 }
 
-fn load_input(file: &str) -> Result<Vec<u16>, Box<dyn Error>> {
+fn load_input(file: &str) -> Result<Vec<Pos>, Box<dyn Error>> {
     // Open the file
     let file = File::open(file)?;
 
@@ -110,7 +106,7 @@ fn load_input(file: &str) -> Result<Vec<u16>, Box<dyn Error>> {
     load_buf(mmap.as_ref())
 }
 
-fn load_buf(buf: &[u8]) -> Result<Vec<u16>, Box<dyn Error>> {
+fn load_buf(buf: &[u8]) -> Result<Vec<Pos>, Box<dyn Error>> {
     // Create buf reader for the buffer
     let buf_reader = BufReader::new(buf);
 
@@ -122,10 +118,10 @@ fn load_buf(buf: &[u8]) -> Result<Vec<u16>, Box<dyn Error>> {
         let line = line_res?;
 
         if !line.is_empty() {
-            let mut nums: Vec<u16> = line
+            let mut nums: Vec<Pos> = line
                 .split(',')
-                .map(|ns| ns.parse::<u16>())
-                .collect::<Result<Vec<u16>, _>>()?;
+                .map(|ns| ns.parse::<Pos>())
+                .collect::<Result<Vec<Pos>, _>>()?;
 
             positions.append(&mut nums);
         }
@@ -136,14 +132,14 @@ fn load_buf(buf: &[u8]) -> Result<Vec<u16>, Box<dyn Error>> {
 
 #[test]
 fn test_parts() {
-    let positions = vec![16,1,2,0,4,2,7,1,2,14];
+    let crabs = Crabs::new(&[16,1,2,0,4,2,7,1,2,14]).unwrap();
 
-    let (pos, fuel) = calculate_hpos1(&positions);
+    let (pos, fuel) = calculate_hpos1(&crabs);
 
     assert_eq!(2, pos, "Optimum position incorrect");
     assert_eq!(37, fuel, "Fuel used incorrect");
 
-    let (pos, fuel) = calculate_hpos2(&positions);
+    let (pos, fuel) = calculate_hpos2(&crabs);
 
     assert_eq!(5, pos, "Optimum position incorrect");
     assert_eq!(168, fuel, "Fuel used incorrect");
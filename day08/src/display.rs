@@ -0,0 +1,287 @@
+use rand::Rng;
+use rand::seq::SliceRandom;
+
+pub type Mask = u32;
+
+// Wires are named with the letters a to z
+pub const MAX_SEGMENTS: usize = 26;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Glyph {
+    pub name: char,
+    pub mask: Mask,
+}
+
+#[derive(Debug, Clone)]
+pub struct Display {
+    pub segments: usize,
+    pub glyphs: Vec<Glyph>,
+}
+
+//  0000
+// 1    2
+// 1    2
+//  3333
+// 4    5
+// 4    5
+//  6666
+
+const SEVEN_SEGMENT: [(char, Mask); 10] = [
+    ('0', 0b1110111),
+    ('1', 0b0100100),
+    ('2', 0b1011101),
+    ('3', 0b1101101),
+    ('4', 0b0101110),
+    ('5', 0b1101011),
+    ('6', 0b1111011),
+    ('7', 0b0100101),
+    ('8', 0b1111111),
+    ('9', 0b1101111),
+];
+
+//    000
+// 5 8 9 A 1
+//   66 77
+// 4 B C D 2
+//    333
+// (8, A, B and D are diagonals, 9 and C are vertical)
+
+const FOURTEEN_SEGMENT: [(char, Mask); 36] = [
+    ('0', 0x0c3f), ('1', 0x0006), ('2', 0x00db), ('3', 0x008f), ('4', 0x00e6),
+    ('5', 0x2069), ('6', 0x00fd), ('7', 0x0007), ('8', 0x00ff), ('9', 0x00ef),
+    ('A', 0x00f7), ('B', 0x128f), ('C', 0x0039), ('D', 0x120f), ('E', 0x00f9),
+    ('F', 0x0071), ('G', 0x00bd), ('H', 0x00f6), ('I', 0x1209), ('J', 0x001e),
+    ('K', 0x2470), ('L', 0x0038), ('M', 0x0536), ('N', 0x2136), ('O', 0x003f),
+    ('P', 0x00f3), ('Q', 0x203f), ('R', 0x20f3), ('S', 0x00ed), ('T', 0x1201),
+    ('U', 0x003e), ('V', 0x0c30), ('W', 0x2836), ('X', 0x2d00), ('Y', 0x1500),
+    ('Z', 0x0c09),
+];
+
+impl Display {
+
+    pub fn new(segments: usize, glyphs: &[(char, Mask)]) -> Result<Self, String> {
+        if !(1..=MAX_SEGMENTS).contains(&segments) {
+            return Err(format!("Displays must have 1 to {} segments, not {}", MAX_SEGMENTS, segments));
+        }
+
+        let display = Display {
+            segments,
+            glyphs: glyphs.iter().map(|&(name, mask)| Glyph { name, mask }).collect(),
+        };
+
+        if let Some(glyph) = display.glyphs.iter().find(|g| g.mask & !display.full_mask() != 0) {
+            return Err(format!("Glyph {} uses segments outside the display", glyph.name));
+        }
+
+        Ok(display)
+    }
+
+    pub fn seven_segment() -> Self {
+        Self::new(7, &SEVEN_SEGMENT).unwrap()
+    }
+
+    pub fn fourteen_segment() -> Self {
+        Self::new(14, &FOURTEEN_SEGMENT).unwrap()
+    }
+
+    pub fn sixteen_segment() -> Self {
+        // Split the top and bottom segments of the fourteen segment display in two.
+        // Top becomes segments 0 and 1, bottom becomes 4 and 5
+        let split = |mask: Mask| {
+            let top = mask & 1;
+            let bottom = (mask >> 3) & 1;
+
+            top | (top << 1) | ((mask & 0b110) << 1) | (bottom << 4) | (bottom << 5) | ((mask >> 4) << 6)
+        };
+
+        let mut glyphs: Vec<(char, Mask)> = FOURTEEN_SEGMENT.iter().map(|&(name, mask)| (name, split(mask))).collect();
+
+        // Brackets use one half of the top and bottom segments
+        glyphs.push(('[', 0b0000_0000_1101_0001));
+        glyphs.push((']', 0b0000_0000_0010_1110));
+
+        Self::new(16, &glyphs).unwrap()
+    }
+
+    pub fn full_mask(&self) -> Mask {
+        Mask::MAX >> (Mask::BITS as usize - self.segments)
+    }
+
+    pub fn glyph_for_mask(&self, mask: Mask) -> Option<&Glyph> {
+        self.glyphs.iter().find(|g| g.mask == mask)
+    }
+
+    pub fn glyph_for_name(&self, name: char) -> Option<&Glyph> {
+        self.glyphs.iter().find(|g| g.name == name)
+    }
+
+    pub fn render(&self, masks: &[Mask]) -> Option<String> {
+        // Only seven segment displays can be rendered
+        if self.segments != 7 {
+            return None;
+        }
+
+        let seg = |mask: Mask, segment: usize, c: char| if mask & (1 << segment) != 0 { c } else { ' ' };
+
+        // Build three lines of text, three characters per digit
+        let mut lines = [String::new(), String::new(), String::new()];
+
+        for &mask in masks {
+            lines[0].extend([' ', seg(mask, 0, '_'), ' ']);
+            lines[1].extend([seg(mask, 1, '|'), seg(mask, 3, '_'), seg(mask, 2, '|')]);
+            lines[2].extend([seg(mask, 4, '|'), seg(mask, 6, '_'), seg(mask, 5, '|')]);
+        }
+
+        Some(lines.iter().map(|l| l.trim_end()).collect::<Vec<_>>().join("\n"))
+    }
+
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Wiring {
+    // Display segment driven by each wire
+    pub segments: Vec<usize>,
+}
+
+impl Wiring {
+
+    pub fn random<R: Rng>(display: &Display, rng: &mut R) -> Self {
+        let mut segments: Vec<usize> = (0..display.segments).collect();
+
+        segments.shuffle(rng);
+
+        Wiring { segments }
+    }
+
+    pub fn wire_char(wire: usize) -> char {
+        (b'a' + wire as u8) as char
+    }
+
+    pub fn wire_index(c: char) -> Option<usize> {
+        if c.is_ascii_lowercase() && ((c as u8 - b'a') as usize) < MAX_SEGMENTS {
+            Some((c as u8 - b'a') as usize)
+        } else {
+            None
+        }
+    }
+
+    pub fn segment_mask(&self, pattern: &str) -> Option<Mask> {
+        pattern.chars().try_fold(0, |mask, c| {
+            let wire = Self::wire_index(c)?;
+
+            Some(mask | (1 << self.segments.get(wire)?))
+        })
+    }
+
+    pub fn decode<'a>(&self, display: &'a Display, pattern: &str) -> Option<&'a Glyph> {
+        display.glyph_for_mask(self.segment_mask(pattern)?)
+    }
+
+    pub fn encode(&self, display: &Display, name: char) -> Option<String> {
+        let glyph = display.glyph_for_name(name)?;
+
+        // Build pattern of wires driving the lit segments
+        Some((0..display.segments)
+            .filter(|&wire| glyph.mask & (1 << self.segments[wire]) != 0)
+            .map(Self::wire_char)
+            .collect())
+    }
+
+    pub fn encode_str(&self, display: &Display, names: &str) -> Option<Vec<String>> {
+        names.chars().map(|name| self.encode(display, name)).collect()
+    }
+
+}
+
+pub fn scramble<R: Rng>(pattern: &str, rng: &mut R) -> String {
+    // Shuffle the order of the wires in a pattern
+    let mut chars: Vec<char> = pattern.chars().collect();
+
+    chars.shuffle(rng);
+
+    chars.into_iter().collect()
+}
+
+pub fn generate_line<R: Rng>(display: &Display, wiring: &Wiring, output: &str, rng: &mut R) -> Option<String> {
+    // Build every glyph in a random order
+    let mut glyphs: Vec<char> = display.glyphs.iter().map(|g| g.name).collect();
+    glyphs.shuffle(rng);
+
+    let mut scrambled = |names: Vec<String>| {
+        names.iter().map(|p| scramble(p, rng)).collect::<Vec<_>>().join(" ")
+    };
+
+    let signals = wiring.encode_str(display, &glyphs.into_iter().collect::<String>())?;
+    let signals = scrambled(signals);
+
+    let outputs = wiring.encode_str(display, output)?;
+    let outputs = scrambled(outputs);
+
+    Some(format!("{} | {}", signals, outputs))
+}
+
+#[test]
+fn test_glyphs_distinct() {
+    for display in [Display::seven_segment(), Display::fourteen_segment(), Display::sixteen_segment()] {
+        for (i, g1) in display.glyphs.iter().enumerate() {
+            assert_eq!(g1.mask & !display.full_mask(), 0, "Glyph {} uses unknown segments", g1.name);
+
+            for g2 in display.glyphs.iter().skip(i + 1) {
+                assert_ne!(g1.mask, g2.mask, "Glyphs {} and {} are the same", g1.name, g2.name);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_new() {
+    assert!(Display::new(0, &[]).is_err());
+    assert!(Display::new(27, &[]).is_err());
+    assert_eq!(Display::new(2, &[('x', 0b100)]).err(), Some("Glyph x uses segments outside the display".to_string()));
+
+    let display = Display::new(26, &[('x', 1 << 25)]).unwrap();
+    assert_eq!(display.full_mask(), (1 << 26) - 1);
+
+    // Every segment has a wire name
+    for wire in 0..MAX_SEGMENTS {
+        assert_eq!(Wiring::wire_index(Wiring::wire_char(wire)), Some(wire));
+    }
+}
+
+#[test]
+fn test_round_trip() {
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    let mut rng = StdRng::seed_from_u64(8);
+
+    for display in [Display::seven_segment(), Display::fourteen_segment(), Display::sixteen_segment()] {
+        let wiring = Wiring::random(&display, &mut rng);
+        let names: String = display.glyphs.iter().map(|g| g.name).collect();
+
+        let patterns = wiring.encode_str(&display, &names).unwrap();
+
+        let decoded: String = patterns.iter()
+            .map(|p| wiring.decode(&display, &scramble(p, &mut rng)).unwrap().name)
+            .collect();
+
+        assert_eq!(decoded, names);
+    }
+
+    // Unknown glyph
+    assert_eq!(Wiring::random(&Display::seven_segment(), &mut rng).encode_str(&Display::seven_segment(), "12A"), None);
+}
+
+#[test]
+fn test_render() {
+    let display = Display::seven_segment();
+    let masks: Vec<Mask> = display.glyphs.iter().map(|g| g.mask).collect();
+
+    assert_eq!(display.render(&masks).unwrap(), [
+        " _     _  _     _  _  _  _  _",
+        "| |  | _| _||_||_ |_   ||_||_|",
+        "|_|  ||_  _|  | _||_|  ||_| _|",
+    ].join("\n"));
+
+    assert_eq!(Display::fourteen_segment().render(&masks), None);
+}
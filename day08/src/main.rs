@@ -1,16 +1,52 @@
-use std::collections::HashSet;
+mod display;
+mod solver;
+
 use std::io::{BufRead, BufReader};
 use std::error::Error;
 use std::fs::File;
 use memmap2::Mmap;
+use rand::seq::SliceRandom;
+
+use display::{Display, Wiring};
+use solver::SolveError;
+
+type Digits = Vec<Vec<String>>;
 
 fn main() -> Result<(), Box<dyn Error>> {
+    // Process options
+    let mut display = Display::seven_segment();
+    let mut render = false;
+    let mut generate = None;
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--display" => {
+                display = match args.next().as_deref() {
+                    Some("7") => Display::seven_segment(),
+                    Some("14") => Display::fourteen_segment(),
+                    Some("16") => Display::sixteen_segment(),
+                    _ => return Err("--display expects 7, 14 or 16".into()),
+                }
+            }
+            "--render" => render = true,
+            "--generate" => generate = Some(args.next().ok_or("--generate expects a line count")?.parse()?),
+            _ => return Err(format!("Unrecognised argument '{}'", arg).into()),
+        }
+    }
+
+    // Generate test data if requested
+    if let Some(lines) = generate {
+        generate_input(&display, lines)?;
+        return Ok(());
+    }
+
     // Load the input file
     let (signals, digits) = load_input("input08.txt")?;
 
     // Run parts
     part1(&digits);
-    part2(&signals, &digits);
+    part2(&display, &signals, &digits, render)?;
 
     Ok(())
 }
@@ -35,171 +71,76 @@ fn count_known_digits(digits: &[Vec<String>]) -> u16 {
     })
 }
 
-fn part2(signals: &[Vec<String>], digits: &[Vec<String>]) {
-    let mut sum: u32 = 0;
+fn part2(display: &Display, signals: &Digits, digits: &Digits, render: bool) -> Result<(), Box<dyn Error>> {
+    let mut sum: u64 = 0;
 
     for elem in 0..signals.len() {
         let signal = &signals[elem];
         let digits = &digits[elem];
 
-        let segments = deduce_segments(signal);
-        let numbers = convert_digits(digits, &segments);
-        let number = digits_to_number(&numbers);
-        sum += number;
+        let wiring = deduce_segments(display, signal)?;
+        let numbers = convert_digits(display, digits, &wiring)?;
+        let number = digits_to_number(&numbers)?;
+        sum = sum.checked_add(number).ok_or("Sum of numbers is too large")?;
+
+        // Draw the decoded reading
+        if render {
+            let masks = digits.iter()
+                .map(|d| wiring.segment_mask(d).ok_or_else(|| format!("Unable to render {}", d)))
+                .collect::<Result<Vec<_>, _>>()?;
+            let art = display.render(&masks).ok_or("Only seven segment displays can be rendered")?;
+
+            println!("{}\n{}\n", number, art);
+        }
     }
 
     println!("Part 2: Sum of numbers: {}", sum);
-}
 
-// Segment deduction
-
-//  1111
-// 2    3
-// 2    3
-//  4444
-// 5    6
-// 5    6
-//  7777
-
-// Digits to segments:
-// 0 - 1 2 3   5 6 7 (6)
-// 1 -     3     6   (2)
-// 2 - 1   3 4 5   7 (5)
-// 3 - 1   3 4   6 7 (5)
-// 4 -   2 3 4   6   (4)
-// 5 - 1 2   4   6 7 (5)
-// 6 - 1 2   4 5 6 7 (6)
-// 7 - 1   3     6   (3)
-// 8 - 1 2 3 4 5 6 7 (7)
-// 9 - 1 2 3 4   6 7 (6)
-
-// A: find signal with length 2 to get 3 & 6
-// B: find signal with length 3. Subtract (A) to find segment 1
-// C: find signal with length 4. Subtract (A) to C1
-// D: find signal with length 6 containing all of (C), digit 9. Segment 5 can be deduced (not present)
-// E: find signal with length 6 containing C1 and not all of A, digit 5. Segments 3 and 6 can be deduced
-// F: find signal with length 6 containing A and not all of C1, digit 0. Segments 2 and 4 can be deduced
-// G: Only segment 7 left
-
-fn deduce_segments(signals: &[String]) -> [char; 7] {
-    let a = signals.iter().find(|s| s.len() == 2).unwrap();
-
-    let seg1 = {
-        let b1 = signals.iter().find(|s| s.len() == 3).unwrap();
-        char_not_in(b1, a)
-    };
-
-    let c = signals.iter().find(|s| s.len() == 4).unwrap();
-    let c1 = str_not_in(c, a);
-
-    let seg5 = {
-        let d1 = signals.iter()
-            .find(|s| s.len() == 6 && str_contains(s, c))
-            .unwrap();
-        char_not_in("abcdefg", d1)
-    };
-
-    let (seg3, seg6) = {
-        let e1 = signals.iter().find(|s| s.len() == 6 && str_contains(s, &c1) && !str_contains(s, a)).unwrap();
-        let e2 = common_char(a, e1);
-        (char_not_in(a, &String::from(e2)), e2)
-    };
-
-    let (seg2, seg4) = {
-        let f1 = signals.iter().find(|s| s.len() == 6 && str_contains(s, a) && !str_contains(s, &c1)).unwrap();
-        let f2 = common_char(&c1, f1);
-        (f2, char_not_in(&c1, &String::from(f2)))
-    };
-
-    let seg7 = {
-        let g1: String = vec![seg1, seg2, seg3, seg4, seg5, seg6].into_iter().collect();
-        char_not_in("abcdefg", &g1)
-    };
-
-    // println!("{} {} {} {} {} {} {} {} {} {}", a, seg1, c, c1, seg5, seg3, seg6, seg2, seg4, seg7);
-
-    [seg1, seg2, seg3, seg4, seg5, seg6, seg7]
+    Ok(())
 }
 
-fn char_not_in(s1: &str, s2: &str) -> char {
-    let c1: HashSet<_> = s1.chars().collect();
-    let c2: HashSet<_> = s2.chars().collect();
-
-    let diff: Vec<&char> = c1.difference(&c2).collect();
-
-    assert!(diff.len() == 1);
-
-    *diff[0]
+fn deduce_segments(display: &Display, signals: &[String]) -> Result<Wiring, SolveError> {
+    solver::solve(display, signals)
 }
 
-fn str_not_in(s1: &str, s2: &str) -> String {
-    let c1: HashSet<_> = s1.chars().collect();
-    let c2: HashSet<_> = s2.chars().collect();
-
-    let diff: String = c1.difference(&c2).collect();
-
-    diff
-}
+fn convert_digits(display: &Display, digits: &[String], wiring: &Wiring) -> Result<Vec<u8>, Box<dyn Error>> {
+    digits.iter().map(|digit| {
+        let glyph = wiring.decode(display, digit)
+            .ok_or_else(|| format!("Unable to decode {}", digit))?;
 
-fn str_contains(s1: &str, s2: &str) -> bool {
-    let c1: HashSet<_> = s1.chars().collect();
-    let c2: HashSet<_> = s2.chars().collect();
+        let value = glyph.name.to_digit(10)
+            .ok_or_else(|| format!("Glyph {} is not a digit", glyph.name))?;
 
-    c1.is_superset(&c2)
+        Ok(value as u8)
+    }).collect()
 }
 
-fn common_char(s1: &str, s2: &str) -> char {
-    let c1: HashSet<_> = s1.chars().collect();
-    let c2: HashSet<_> = s2.chars().collect();
+fn generate_input(display: &Display, lines: usize) -> Result<(), Box<dyn Error>> {
+    let mut rng = rand::thread_rng();
+    let names: Vec<char> = display.glyphs.iter().map(|g| g.name).collect();
 
-    let intersection: Vec<&char> = c1.intersection(&c2).collect();
+    for _ in 0..lines {
+        // Choose a random wiring and four random glyphs
+        let wiring = Wiring::random(display, &mut rng);
+        let output: String = (0..4).map(|_| *names.choose(&mut rng).unwrap()).collect();
 
-    assert!(intersection.len() == 1);
+        let line = display::generate_line(display, &wiring, &output, &mut rng).ok_or("Unable to encode output")?;
 
-    *intersection[0]
-}
-
-const NUM_SEGMENTS: [u8; 10] = [
-    0b1110111, // 0
-    0b0100100, // 1
-    0b1011101, // 2
-    0b1101101, // 3
-    0b0101110, // 4
-    0b1101011, // 5
-    0b1111011, // 6
-    0b0100101, // 7
-    0b1111111, // 8
-    0b1101111, // 9
-];
-
-fn convert_digits(digits: &[String], segments: &[char; 7]) -> Vec<u8> {
-    let mut result: Vec<u8> = Vec::with_capacity(digits.len());
-
-    for digit in digits {
-        let find_digit = digit
-            .chars()
-            .map(|c| segments.iter().position(|&s| s == c).unwrap())
-            .fold(0u8, |acc, seg| acc + (1 << seg));
-
-        let digit = NUM_SEGMENTS.iter().position(|&segs| segs == find_digit).unwrap() as u8;
-
-        result.push(digit);
+        println!("{}", line);
     }
 
-    result
+    Ok(())
 }
 
-fn digits_to_number(digits: &[u8]) -> u32 {
+fn digits_to_number(digits: &[u8]) -> Result<u64, String> {
+    // Readings can have any number of digits, so check they fit
     digits
         .iter()
-        .rev()
-        .enumerate()
-        .fold(0, |acc, (elem, &digit)| {
-            acc + (digit as u32 * (10u32.pow(elem as u32)))
-        })
+        .try_fold(0u64, |acc, &digit| acc.checked_mul(10)?.checked_add(digit as u64))
+        .ok_or_else(|| format!("Reading with {} digits is too large", digits.len()))
 }
 
-type ParseResult = (Vec<Vec<String>>, Vec<Vec<String>>);
+type ParseResult = (Digits, Digits);
 
 fn load_input(file: &str) -> Result<ParseResult, Box<dyn Error>> {
     // Open the file
@@ -245,15 +186,25 @@ fn load_buf(buf: &[u8]) -> Result<ParseResult, Box<dyn Error>> {
 fn test_part2() {
     let (signals, digits) = load_buf("acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagedb ab | cdfeb fcadb cdfeb cdbaf".as_bytes()).unwrap();
 
-    let segments = deduce_segments(&signals[0]);
+    let display = Display::seven_segment();
+    let wiring = deduce_segments(&display, &signals[0]).unwrap();
+
+    let segments: Vec<char> = (0..7)
+        .map(|seg| Wiring::wire_char(wiring.segments.iter().position(|&s| s == seg).unwrap()))
+        .collect();
 
     assert_eq!(segments, ['d', 'e', 'a', 'f', 'g', 'b', 'c']);
 
-    let numbers = convert_digits(&digits[0], &segments);
+    let numbers = convert_digits(&display, &digits[0], &wiring).unwrap();
 
     assert_eq!(numbers, [5, 3, 5, 3]);
 
-    let number = digits_to_number(&numbers);
+    let number = digits_to_number(&numbers).unwrap();
 
     assert_eq!(5353, number);
+
+    // Long readings
+    assert_eq!(digits_to_number(&[1, 8, 4, 4, 6, 7, 4, 4, 0, 7, 3, 7, 0, 9, 5, 5, 1, 6, 1, 5]), Ok(u64::MAX));
+    assert!(digits_to_number(&[1, 8, 4, 4, 6, 7, 4, 4, 0, 7, 3, 7, 0, 9, 5, 5, 1, 6, 1, 6]).is_err());
+    assert!(digits_to_number(&[9; 25]).is_err());
 }
@@ -0,0 +1,218 @@
+use std::error::Error;
+use std::fmt;
+
+use super::display::{Display, Mask, Wiring};
+
+#[derive(Debug, PartialEq)]
+pub enum SolveError {
+    UnknownWire(char),
+    Contradictory,
+    Ambiguous(Vec<Wiring>),
+}
+
+impl fmt::Display for SolveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SolveError::UnknownWire(c) => write!(f, "unknown wire '{}' in observation", c),
+            SolveError::Contradictory => write!(f, "observations are contradictory"),
+            SolveError::Ambiguous(_) => write!(f, "observations are ambiguous"),
+        }
+    }
+}
+
+impl Error for SolveError {
+}
+
+struct Solver<'a> {
+    display: &'a Display,
+    // Wires present in each observation
+    observations: Vec<Mask>,
+}
+
+#[derive(Clone)]
+struct State {
+    // Possible segments for each wire
+    domains: Vec<Mask>,
+    // Possible glyphs for each observation
+    candidates: Vec<Vec<Mask>>,
+}
+
+pub fn solve(display: &Display, observations: &[String]) -> Result<Wiring, SolveError> {
+    let wires = display.segments;
+
+    // Convert observations to wire masks
+    let observations = observations.iter().map(|o| {
+        o.chars().try_fold(0, |mask, c| match Wiring::wire_index(c) {
+            Some(wire) if wire < wires => Ok(mask | (1 << wire)),
+            _ => Err(SolveError::UnknownWire(c)),
+        })
+    }).collect::<Result<Vec<Mask>, _>>()?;
+
+    // Initial candidate glyphs have the same number of segments lit
+    let candidates = observations.iter().map(|o| {
+        display.glyphs.iter()
+            .filter(|g| g.mask.count_ones() == o.count_ones())
+            .map(|g| g.mask)
+            .collect()
+    }).collect();
+
+    let state = State {
+        domains: vec![display.full_mask(); wires],
+        candidates,
+    };
+
+    let solver = Solver { display, observations };
+
+    // Find up to two solutions
+    let mut solutions = Vec::new();
+    solver.search(state, &mut solutions, 2);
+
+    match solutions.len() {
+        0 => Err(SolveError::Contradictory),
+        1 => Ok(solutions.pop().unwrap()),
+        _ => Err(SolveError::Ambiguous(solutions)),
+    }
+}
+
+impl<'a> Solver<'a> {
+
+    fn search(&self, mut state: State, solutions: &mut Vec<Wiring>, limit: usize) {
+        if !self.propagate(&mut state) {
+            return;
+        }
+
+        // Find the undecided wire with the fewest possible segments
+        let undecided = state.domains.iter().enumerate()
+            .filter(|(_, d)| d.count_ones() > 1)
+            .min_by_key(|(_, d)| d.count_ones());
+
+        match undecided {
+            None => {
+                // All wires decided
+                let segments = state.domains.iter().map(|d| d.trailing_zeros() as usize).collect();
+
+                solutions.push(Wiring { segments });
+            }
+            Some((wire, &domain)) => {
+                // Try each possible segment for the wire
+                for segment in 0..self.display.segments {
+                    if domain & (1 << segment) == 0 {
+                        continue;
+                    }
+
+                    let mut next = state.clone();
+                    next.domains[wire] = 1 << segment;
+
+                    self.search(next, solutions, limit);
+
+                    if solutions.len() >= limit {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    fn propagate(&self, state: &mut State) -> bool {
+        let full = self.display.full_mask();
+
+        loop {
+            let before = state.domains.clone();
+
+            for (obs, candidates) in self.observations.iter().zip(state.candidates.iter_mut()) {
+                // Remove glyphs which can't be lit by the observed wires
+                candidates.retain(|&glyph| {
+                    state.domains.iter().enumerate().all(|(wire, &domain)| {
+                        if obs & (1 << wire) != 0 {
+                            domain & glyph != 0
+                        } else {
+                            domain & !glyph & full != 0
+                        }
+                    })
+                });
+
+                if candidates.is_empty() {
+                    return false;
+                }
+
+                // Restrict wires to segments of the remaining glyphs
+                let lit = candidates.iter().fold(0, |acc, g| acc | g);
+                let unlit = candidates.iter().fold(0, |acc, g| acc | (!g & full));
+
+                for (wire, domain) in state.domains.iter_mut().enumerate() {
+                    if obs & (1 << wire) != 0 {
+                        *domain &= lit;
+                    } else {
+                        *domain &= unlit;
+                    }
+                }
+            }
+
+            // Each segment is driven by one wire only
+            for wire in 0..state.domains.len() {
+                let domain = state.domains[wire];
+
+                if domain.count_ones() == 1 {
+                    for (other, other_domain) in state.domains.iter_mut().enumerate() {
+                        if other != wire {
+                            *other_domain &= !domain;
+                        }
+                    }
+                }
+            }
+
+            if state.domains.contains(&0) {
+                return false;
+            }
+
+            if state.domains == before {
+                return true;
+            }
+        }
+    }
+
+}
+
+#[test]
+fn test_seven_segment() {
+    let display = Display::seven_segment();
+    let observations: Vec<String> = "acedgfb cdfbe gcdfa fbcad dab cefabd cdfgeb eafb cagedb ab"
+        .split_whitespace().map(String::from).collect();
+
+    let wiring = solve(&display, &observations).unwrap();
+
+    let decoded: String = ["cdfeb", "fcadb", "cdfeb", "cdbaf"].iter()
+        .map(|p| wiring.decode(&display, p).unwrap().name)
+        .collect();
+
+    assert_eq!(decoded, "5353");
+
+    // Too few observations
+    assert!(matches!(solve(&display, &observations[..3]), Err(SolveError::Ambiguous(_))));
+
+    // More six segment observations than six segment glyphs
+    let mut bad = observations.clone();
+    bad[0] = "abcdef".to_string();
+    bad[1] = "abcdeg".to_string();
+    bad[2] = "abcdfg".to_string();
+    bad[3] = "abcefg".to_string();
+    assert_eq!(solve(&display, &bad), Err(SolveError::Contradictory));
+
+    // Wire out of range
+    assert_eq!(solve(&display, &["ah".to_string()]), Err(SolveError::UnknownWire('h')));
+}
+
+#[test]
+fn test_other_displays() {
+    for display in [Display::fourteen_segment(), Display::sixteen_segment()] {
+        // Scramble the wires by reversing them
+        let segments: Vec<usize> = (0..display.segments).rev().collect();
+        let wiring = Wiring { segments };
+
+        // Observe every glyph
+        let names: String = display.glyphs.iter().map(|g| g.name).collect();
+        let observations = wiring.encode_str(&display, &names).unwrap();
+
+        assert_eq!(solve(&display, &observations), Ok(wiring));
+    }
+}
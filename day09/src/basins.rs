@@ -0,0 +1,233 @@
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Connectivity {
+    Four,
+    Eight,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct LabelConfig {
+    // Heights at or above this are walls
+    pub wall: u8,
+    pub connectivity: Connectivity,
+}
+
+impl Default for LabelConfig {
+    fn default() -> Self {
+        LabelConfig {
+            wall: 9,
+            connectivity: Connectivity::Four,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BasinStats {
+    pub size: usize,
+    pub min_height: u8,
+    // First cell at the minimum height
+    pub low_point: (usize, usize),
+    // Inclusive bounding box (min x, min y, max x, max y)
+    pub bbox: (usize, usize, usize, usize),
+    // Number of cell edges bordering a wall or the edge of the map
+    pub perimeter: usize,
+}
+
+#[derive(Debug)]
+pub struct Labelling {
+    // Basin number of each cell, None for walls
+    pub labels: Vec<Vec<Option<usize>>>,
+    pub basins: Vec<BasinStats>,
+}
+
+struct UnionFind {
+    parent: Vec<usize>,
+}
+
+impl UnionFind {
+
+    fn add(&mut self) -> usize {
+        let id = self.parent.len();
+
+        self.parent.push(id);
+
+        id
+    }
+
+    fn find(&mut self, mut id: usize) -> usize {
+        // Find root, halving the path as we go
+        while self.parent[id] != id {
+            self.parent[id] = self.parent[self.parent[id]];
+            id = self.parent[id];
+        }
+
+        id
+    }
+
+    fn union(&mut self, a: usize, b: usize) -> usize {
+        let ra = self.find(a);
+        let rb = self.find(b);
+
+        // Keep the lowest id as the root
+        let (root, child) = if ra < rb { (ra, rb) } else { (rb, ra) };
+
+        self.parent[child] = root;
+
+        root
+    }
+
+}
+
+pub fn label_basins(heights: &[Vec<u8>], config: &LabelConfig) -> Labelling {
+    let width = heights[0].len();
+    let height = heights.len();
+
+    let mut uf = UnionFind { parent: Vec::new() };
+    let mut cells: Vec<Vec<Option<usize>>> = vec![vec![None; width]; height];
+
+    // Previously visited neighbours in raster order
+    let prev: &[(isize, isize)] = match config.connectivity {
+        Connectivity::Four => &[(-1, 0), (0, -1)],
+        Connectivity::Eight => &[(-1, 0), (-1, -1), (0, -1), (1, -1)],
+    };
+
+    // Single pass, joining each cell with its previously visited neighbours
+    for y in 0..height {
+        for x in 0..width {
+            if heights[y][x] >= config.wall {
+                continue;
+            }
+
+            let mut id = None;
+
+            for &(dx, dy) in prev {
+                let nx = x as isize + dx;
+                let ny = y as isize + dy;
+
+                if nx < 0 || ny < 0 || nx >= width as isize {
+                    continue;
+                }
+
+                if let Some(nid) = cells[ny as usize][nx as usize] {
+                    id = Some(match id {
+                        None => nid,
+                        Some(id) => uf.union(id, nid),
+                    });
+                }
+            }
+
+            cells[y][x] = Some(id.unwrap_or_else(|| uf.add()));
+        }
+    }
+
+    // Resolve roots to basin numbers and gather statistics
+    let mut basin_of_root = vec![None; uf.parent.len()];
+    let mut basins: Vec<BasinStats> = Vec::new();
+    let mut labels: Vec<Vec<Option<usize>>> = vec![vec![None; width]; height];
+
+    for y in 0..height {
+        for x in 0..width {
+            let Some(id) = cells[y][x] else {
+                continue;
+            };
+
+            let root = uf.find(id);
+
+            let basin = *basin_of_root[root].get_or_insert_with(|| {
+                basins.push(BasinStats {
+                    size: 0,
+                    min_height: u8::MAX,
+                    low_point: (x, y),
+                    bbox: (x, y, x, y),
+                    perimeter: 0,
+                });
+
+                basins.len() - 1
+            });
+
+            labels[y][x] = Some(basin);
+
+            let stats = &mut basins[basin];
+            let h = heights[y][x];
+
+            stats.size += 1;
+
+            if h < stats.min_height {
+                stats.min_height = h;
+                stats.low_point = (x, y);
+            }
+
+            stats.bbox.0 = stats.bbox.0.min(x);
+            stats.bbox.1 = stats.bbox.1.min(y);
+            stats.bbox.2 = stats.bbox.2.max(x);
+            stats.bbox.3 = stats.bbox.3.max(y);
+
+            // Count edges bordering walls or the map edge
+            let open = |nx: usize, ny: usize| heights[ny][nx] < config.wall;
+
+            if x == 0 || !open(x - 1, y) { stats.perimeter += 1 };
+            if y == 0 || !open(x, y - 1) { stats.perimeter += 1 };
+            if x == width - 1 || !open(x + 1, y) { stats.perimeter += 1 };
+            if y == height - 1 || !open(x, y + 1) { stats.perimeter += 1 };
+        }
+    }
+
+    Labelling { labels, basins }
+}
+
+#[test]
+fn test_labelling() {
+    let heights: Vec<Vec<u8>> = [
+        "2199943210",
+        "3987894921",
+        "9856789892",
+        "8767896789",
+        "9899965678",
+    ].iter().map(|l| l.bytes().map(|b| b - b'0').collect()).collect();
+
+    let labelling = label_basins(&heights, &LabelConfig::default());
+
+    let mut sizes: Vec<usize> = labelling.basins.iter().map(|b| b.size).collect();
+    sizes.sort();
+    assert_eq!(sizes, vec![3, 9, 9, 14]);
+
+    let top_left = &labelling.basins[labelling.labels[0][0].unwrap()];
+    assert_eq!(*top_left, BasinStats {
+        size: 3,
+        min_height: 1,
+        low_point: (1, 0),
+        bbox: (0, 0, 1, 1),
+        perimeter: 8,
+    });
+
+    assert_eq!(labelling.labels[0][2], None);
+
+    // Diagonal connections join basins
+    let labelling = label_basins(&heights, &LabelConfig { connectivity: Connectivity::Eight, ..Default::default() });
+    assert_eq!(labelling.basins.len(), 1);
+
+    // Lower walls split basins
+    let labelling = label_basins(&heights, &LabelConfig { wall: 8, ..Default::default() });
+    let mut sizes: Vec<usize> = labelling.basins.iter().map(|b| b.size).collect();
+    sizes.sort();
+    assert_eq!(sizes, vec![3, 6, 7, 9]);
+}
+
+#[test]
+fn test_plateau() {
+    // Basin with no strict low point
+    let heights = vec![
+        vec![9, 9, 9, 9],
+        vec![9, 4, 4, 9],
+        vec![9, 9, 9, 9],
+    ];
+
+    let labelling = label_basins(&heights, &LabelConfig::default());
+
+    assert_eq!(labelling.basins, vec![BasinStats {
+        size: 2,
+        min_height: 4,
+        low_point: (1, 1),
+        bbox: (1, 1, 2, 1),
+        perimeter: 6,
+    }]);
+}
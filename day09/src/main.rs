@@ -1,21 +1,56 @@
-use std::collections::{VecDeque, HashSet};
-use std::io::{BufRead, BufReader, BufWriter};
+mod basins;
+mod render;
+
+use std::io::{BufRead, BufReader};
 use std::error::Error;
-use std::path::Path;
 use std::fs::File;
 use memmap2::Mmap;
 
+use basins::{Connectivity, LabelConfig};
+
 fn main() -> Result<(), Box<dyn Error>> {
+    // Process options
+    let mut config = LabelConfig::default();
+    let mut input = "input09.txt".to_string();
+    let mut map_png = "output09-1.png".to_string();
+    let mut basin_png = "output09-2.png".to_string();
+    let mut scale = 1;
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--wall" => config.wall = args.next().ok_or("--wall expects a height")?.parse()?,
+            "--connectivity" => {
+                config.connectivity = match args.next().as_deref() {
+                    Some("4") => Connectivity::Four,
+                    Some("8") => Connectivity::Eight,
+                    _ => return Err("--connectivity expects 4 or 8".into()),
+                }
+            }
+            "--input" => input = args.next().ok_or("--input expects a file name")?,
+            "--map-png" => map_png = args.next().ok_or("--map-png expects a file name")?,
+            "--basin-png" => basin_png = args.next().ok_or("--basin-png expects a file name")?,
+            "--scale" => {
+                scale = args.next().ok_or("--scale expects a factor")?.parse()?;
+
+                if scale == 0 {
+                    return Err("--scale must be at least 1".into());
+                }
+            }
+            _ => return Err(format!("Unrecognised argument '{}'", arg).into()),
+        }
+    }
+
     // Load the input file
-    let heights = load_input("input09.txt")?;
+    let heights = load_input(&input)?;
 
     // Run parts
     part1(&heights);
-    part2(&heights);
+    part2(&heights, &config);
 
-    // Generate map PNG
-    gen_map_png(&heights, "output09-1.png");
-    gen_basin_png(&heights, "output09-2.png");
+    // Generate map PNGs
+    render::gen_map_png(&heights, scale, &map_png)?;
+    render::gen_basin_png(&basins::label_basins(&heights, &config), scale, &basin_png)?;
 
     Ok(())
 }
@@ -28,10 +63,8 @@ fn part1(heights: &[Vec<u8>]) {
     println!("Part 1: Risk level sum: {}", risk);
 }
 
-fn part2(heights: &[Vec<u8>]) {
-    let low_points = find_low_points(heights);
-
-    let basins = build_basins(heights, low_points);
+fn part2(heights: &[Vec<u8>], config: &LabelConfig) {
+    let basins = build_basins(heights, config);
 
     let result = basins.iter().take(3).fold(1, |acc, b| acc * b.size);
 
@@ -73,46 +106,13 @@ struct Basin {
     y: usize,
 }
 
-impl Basin {
-
-    fn build_from(x: usize, y: usize, heights: &[Vec<u8>]) -> Self {
-        let width = heights[0].len();
-        let height = heights.len();
-    
-        let mut size = 0;
-        let mut visited: HashSet<(usize, usize)> = HashSet::new();
-        let mut visit_queue: VecDeque<(usize, usize)> = VecDeque::new();
-    
-        let mut visit = |x: usize, y: usize, visit_queue: &mut VecDeque<(usize, usize)>| {
-            if heights[y][x] < 9 {
-                let coord = (x, y);
-    
-                if !visited.contains(&coord) {
-                    visit_queue.push_back(coord);
-                    visited.insert(coord);
-                }
-            }
-        };
-    
-        visit(x, y, &mut visit_queue);
-    
-        while let Some((x, y)) = visit_queue.pop_front() {
-            size += 1;
-    
-            if y > 0 { visit(x, y - 1, &mut visit_queue) };
-            if x > 0 { visit(x - 1, y, &mut visit_queue) };
-            if y < height - 1 { visit(x, y + 1, &mut visit_queue) };
-            if x < width - 1 { visit(x + 1, y, &mut visit_queue) };
-        }
-    
-        Self { x, y, size }    
-    }
+fn build_basins(heights: &[Vec<u8>], config: &LabelConfig) -> Vec<Basin> {
+    let labelling = basins::label_basins(heights, config);
 
-}
+    let mut basins: Vec<Basin> = labelling.basins.iter().map(|stats| {
+        let (x, y) = stats.low_point;
 
-fn build_basins(heights: &[Vec<u8>], low_points: Vec<(usize, usize)>) -> Vec<Basin> {
-    let mut basins: Vec<Basin> = low_points.iter().map(|&(x, y)| {
-        Basin::build_from(x, y, heights)
+        Basin { x, y, size: stats.size }
     }).collect();
 
     basins.sort();
@@ -121,65 +121,6 @@ fn build_basins(heights: &[Vec<u8>], low_points: Vec<(usize, usize)>) -> Vec<Bas
     basins
 }
 
-fn gen_map_png(heights: &[Vec<u8>], filename: &str) {
-    let width = heights[0].len();
-    let height = heights.len();
-
-    let path = Path::new(filename);
-    let file = File::create(path).unwrap();
-    let buf_writer = &mut BufWriter::new(file);
-    let mut encoder = png::Encoder::new(buf_writer, width as u32, height as u32);
-
-    encoder.set_color(png::ColorType::Indexed);
-    encoder.set_depth(png::BitDepth::Four);
-    encoder.set_palette(vec![
-        0x01, 0x2a, 0x4a,
-        0x01, 0x3a, 0x63,
-        0x01, 0x49, 0x7c,
-        0x01, 0x4f, 0x86,
-        0x2a, 0x6f, 0x97,
-        0x2c, 0x7d, 0xa0,
-        0x46, 0x8f, 0xaf,
-        0x61, 0xa5, 0xc2,
-        0x89, 0xc2, 0xd9,
-        0xa9, 0xd6, 0xe5,
-    ]);
-
-    let mut writer = encoder.write_header().unwrap();
-    let data: Vec<u8> = heights.iter().flatten().cloned().collect();
-    let four_bit_data: Vec<u8> = data.chunks(2).map(|a| a[0] << 4 | a[1]).collect();
-    writer.write_image_data(&four_bit_data).unwrap();
-}
-
-fn gen_basin_png(heights: &[Vec<u8>], filename: &str) {
-    let width = heights[0].len();
-    let height = heights.len();
-
-    let path = Path::new(filename);
-    let file = File::create(path).unwrap();
-    let buf_writer = &mut BufWriter::new(file);
-    let mut encoder = png::Encoder::new(buf_writer, width as u32, height as u32);
-
-    encoder.set_color(png::ColorType::Indexed);
-    encoder.set_depth(png::BitDepth::One);
-    encoder.set_palette(vec![
-        0x00, 0x00, 0x00,
-        0xff, 0xff, 0xff,
-    ]);
-
-    let mut writer = encoder.write_header().unwrap();
-
-    let one_bit_data: Vec<u8> = heights.iter().flat_map(|row| {
-        let rim: Vec<u8> = row.iter().map(|&h| if h == 9 { 1 } else { 0 }).collect();
-
-        rim.chunks(8).map(|a| {
-            a.iter().fold(0, |acc, bit| acc << 1 | bit)
-        }).collect::<Vec<u8>>()
-    }).collect();
-
-    writer.write_image_data(&one_bit_data).unwrap();
-}
-
 fn load_input(file: &str) -> Result<ParseResult, Box<dyn Error>> {
     // Open the file
     let file = File::open(file)?;
@@ -226,9 +167,7 @@ fn test_part2() {
 
     let heights = load_buf(test_input.as_bytes()).unwrap();
 
-    let low_points = find_low_points(&heights);
-
-    let basins = build_basins(&heights, low_points);
+    let basins = build_basins(&heights, &LabelConfig::default());
 
     assert_eq!(basins, vec![
         Basin { size: 14, x: 2, y: 2 },
@@ -0,0 +1,143 @@
+use std::error::Error;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use super::basins::Labelling;
+
+const MAP_PALETTE: [u8; 30] = [
+    0x01, 0x2a, 0x4a,
+    0x01, 0x3a, 0x63,
+    0x01, 0x49, 0x7c,
+    0x01, 0x4f, 0x86,
+    0x2a, 0x6f, 0x97,
+    0x2c, 0x7d, 0xa0,
+    0x46, 0x8f, 0xaf,
+    0x61, 0xa5, 0xc2,
+    0x89, 0xc2, 0xd9,
+    0xa9, 0xd6, 0xe5,
+];
+
+const WALL_COLOUR: [u8; 3] = [0x00, 0x00, 0x00];
+const LOW_POINT_COLOUR: [u8; 3] = [0xff, 0xff, 0xff];
+
+pub fn gen_map_png(heights: &[Vec<u8>], scale: usize, filename: &str) -> Result<(), Box<dyn Error>> {
+    let rows = scale_rows(heights, scale);
+
+    // Pack two pixels per byte, each row padded to a whole byte
+    let data: Vec<u8> = rows.iter().flat_map(|row| {
+        row.chunks(2).map(|a| (a[0].min(9) << 4) | a.get(1).map_or(0, |&h| h.min(9))).collect::<Vec<u8>>()
+    }).collect();
+
+    write_png(filename, &rows, png::ColorType::Indexed, png::BitDepth::Four, Some(&MAP_PALETTE), &data)
+}
+
+pub fn gen_basin_png(labelling: &Labelling, scale: usize, filename: &str) -> Result<(), Box<dyn Error>> {
+    // Find the three largest basins
+    let mut by_size: Vec<usize> = (0..labelling.basins.len()).collect();
+    by_size.sort_by_key(|&b| std::cmp::Reverse(labelling.basins[b].size));
+
+    let colours: Vec<[u8; 3]> = (0..labelling.basins.len()).map(|b| {
+        basin_colour(b, by_size.iter().take(3).any(|&l| l == b))
+    }).collect();
+
+    // Colour each cell
+    let mut pixels: Vec<Vec<[u8; 3]>> = labelling.labels.iter().map(|row| {
+        row.iter().map(|label| match label {
+            Some(b) => colours[*b],
+            None => WALL_COLOUR,
+        }).collect()
+    }).collect();
+
+    // Mark low points
+    for stats in &labelling.basins {
+        let (x, y) = stats.low_point;
+
+        pixels[y][x] = LOW_POINT_COLOUR;
+    }
+
+    let rows = scale_rows(&pixels, scale);
+    let data: Vec<u8> = rows.iter().flatten().flatten().cloned().collect();
+
+    write_png(filename, &rows, png::ColorType::Rgb, png::BitDepth::Eight, None, &data)
+}
+
+fn basin_colour(basin: usize, highlight: bool) -> [u8; 3] {
+    // Step round the hue circle by the golden ratio to keep neighbouring basins distinct
+    let hue = (basin as f64 * 0.618_033_988_75).fract() * 6.0;
+
+    let (sat, val) = if highlight { (1.0, 1.0) } else { (0.35, 0.6) };
+
+    // Convert HSV to RGB
+    let c = val * sat;
+    let x = c * (1.0 - ((hue % 2.0) - 1.0).abs());
+    let m = val - c;
+
+    let (r, g, b) = match hue as u8 {
+        0 => (c, x, 0.0),
+        1 => (x, c, 0.0),
+        2 => (0.0, c, x),
+        3 => (0.0, x, c),
+        4 => (x, 0.0, c),
+        _ => (c, 0.0, x),
+    };
+
+    let to_byte = |v: f64| ((v + m) * 255.0).round() as u8;
+
+    [to_byte(r), to_byte(g), to_byte(b)]
+}
+
+fn scale_rows<T: Clone>(rows: &[Vec<T>], scale: usize) -> Vec<Vec<T>> {
+    // Repeat each pixel scale times in both directions
+    rows.iter().flat_map(|row| {
+        let scaled: Vec<T> = row.iter().flat_map(|p| std::iter::repeat_n(p.clone(), scale)).collect();
+
+        std::iter::repeat_n(scaled, scale)
+    }).collect()
+}
+
+fn write_png<T>(filename: &str, rows: &[Vec<T>], colour: png::ColorType, depth: png::BitDepth,
+    palette: Option<&[u8]>, data: &[u8]) -> Result<(), Box<dyn Error>> {
+    let path = Path::new(filename);
+    let file = File::create(path)?;
+    let buf_writer = &mut BufWriter::new(file);
+    let mut encoder = png::Encoder::new(buf_writer, rows[0].len() as u32, rows.len() as u32);
+
+    encoder.set_color(colour);
+    encoder.set_depth(depth);
+
+    if let Some(palette) = palette {
+        encoder.set_palette(palette.to_vec());
+    }
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(data)?;
+
+    Ok(())
+}
+
+#[test]
+fn test_scale_rows() {
+    let rows = vec![vec![1, 2], vec![3, 4]];
+
+    assert_eq!(scale_rows(&rows, 1), rows);
+    assert_eq!(scale_rows(&rows, 2), vec![
+        vec![1, 1, 2, 2],
+        vec![1, 1, 2, 2],
+        vec![3, 3, 4, 4],
+        vec![3, 3, 4, 4],
+    ]);
+}
+
+#[test]
+fn test_basin_colours() {
+    // Neighbouring basins get different colours and highlighting changes the colour
+    let colours: Vec<[u8; 3]> = (0..20).map(|b| basin_colour(b, false)).collect();
+
+    for pair in colours.windows(2) {
+        assert_ne!(pair[0], pair[1]);
+    }
+
+    assert_ne!(basin_colour(3, false), basin_colour(3, true));
+    assert!(colours.iter().all(|&c| c != WALL_COLOUR && c != LOW_POINT_COLOUR));
+}
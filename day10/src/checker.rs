@@ -0,0 +1,465 @@
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pair {
+    pub open: String,
+    pub close: String,
+    // Score when the close delimiter is found out of place
+    pub corruption_score: usize,
+    // Score of the close delimiter when completing a line
+    pub completion_score: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OtherChars {
+    Ignore,
+    Error,
+}
+
+pub const MAX_PAIRS: usize = u8::MAX as usize + 1;
+
+#[derive(Debug, Clone)]
+pub struct Delimiters {
+    pub pairs: Vec<Pair>,
+    pub other: OtherChars,
+    // Delimiter chars, pair number and open flag, longest first
+    tokens: Vec<(Vec<char>, usize, bool)>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum LineReport {
+    Valid,
+    Corrupted {
+        // Zero based column of the illegal delimiter
+        column: usize,
+        // Close delimiter expected, None if nothing was open
+        expected: Option<String>,
+        found: String,
+    },
+    Invalid {
+        // Zero based column of the character which is not part of a delimiter
+        column: usize,
+        found: char,
+    },
+    Incomplete {
+        // Close delimiters needed to complete the line
+        completion: Vec<String>,
+    },
+}
+
+impl fmt::Display for LineReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LineReport::Valid => write!(f, "valid"),
+            LineReport::Corrupted { column, expected: Some(expected), found } =>
+                write!(f, "corrupted at column {}, expected {} but found {}", column + 1, expected, found),
+            LineReport::Corrupted { column, expected: None, found } =>
+                write!(f, "corrupted at column {}, found {} with nothing open", column + 1, found),
+            LineReport::Invalid { column, found } =>
+                write!(f, "invalid character {} at column {}", found, column + 1),
+            LineReport::Incomplete { completion } => write!(f, "incomplete, complete with {}", completion.concat()),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ConfigError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for ConfigError {
+}
+
+impl Default for Delimiters {
+    fn default() -> Self {
+        Self::new(vec![
+            Pair::new("(", ")", 3, 1),
+            Pair::new("[", "]", 57, 2),
+            Pair::new("{", "}", 1197, 3),
+            Pair::new("<", ">", 25137, 4),
+        ], OtherChars::Error).unwrap()
+    }
+}
+
+impl Pair {
+
+    pub fn new(open: &str, close: &str, corruption_score: usize, completion_score: usize) -> Self {
+        Pair {
+            open: open.to_string(),
+            close: close.to_string(),
+            corruption_score,
+            completion_score,
+        }
+    }
+
+}
+
+impl Delimiters {
+
+    pub fn new(pairs: Vec<Pair>, other: OtherChars) -> Result<Self, String> {
+        // Open pairs are stacked as bytes
+        if pairs.len() > MAX_PAIRS {
+            return Err(format!("at most {} delimiter pairs are allowed", MAX_PAIRS));
+        }
+
+        let mut tokens: Vec<(Vec<char>, usize, bool)> = Vec::new();
+
+        for (i, pair) in pairs.iter().enumerate() {
+            for (token, open) in [(&pair.open, true), (&pair.close, false)] {
+                if let Some(message) = Self::check_token(&tokens, token) {
+                    return Err(message);
+                }
+
+                tokens.push((token.chars().collect(), i, open));
+            }
+        }
+
+        // Match the longest delimiter first
+        tokens.sort_by_key(|(chars, _, _)| std::cmp::Reverse(chars.len()));
+
+        Ok(Delimiters { pairs, other, tokens })
+    }
+
+    fn check_token(tokens: &[(Vec<char>, usize, bool)], token: &str) -> Option<String> {
+        // Each delimiter must identify a single pair and whether it opens or closes
+        if token.is_empty() {
+            Some("empty delimiter".to_string())
+        } else if tokens.iter().any(|(chars, _, _)| chars.iter().copied().eq(token.chars())) {
+            Some(format!("delimiter {} is used more than once", token))
+        } else {
+            None
+        }
+    }
+
+    pub fn parse(config: &str) -> Result<Self, ConfigError> {
+        // Each line is either "<open> <close> <corruption score> <completion score>"
+        // or "other ignore|error". Blank lines and lines starting with # are skipped
+        let mut pairs = Vec::new();
+        let mut other = OtherChars::Error;
+
+        for (line_no, line) in config.lines().enumerate() {
+            let error = |message: &str| ConfigError { line: line_no + 1, message: message.to_string() };
+
+            let fields: Vec<&str> = line.split_whitespace().collect();
+
+            match fields[..] {
+                [] => (),
+                [first, ..] if first.starts_with('#') => (),
+                ["other", "ignore"] => other = OtherChars::Ignore,
+                ["other", "error"] => other = OtherChars::Error,
+                ["other", ..] => return Err(error("other expects ignore or error")),
+                [open, close, corruption, completion] => {
+                    if open == close {
+                        return Err(error("open and close delimiters must differ"));
+                    }
+
+                    for token in [open, close] {
+                        if pairs.iter().any(|p: &Pair| p.open == token || p.close == token) {
+                            return Err(error(&format!("delimiter {} is used more than once", token)));
+                        }
+                    }
+
+                    let corruption = corruption.parse().map_err(|_| error("invalid corruption score"))?;
+                    let completion = completion.parse().map_err(|_| error("invalid completion score"))?;
+
+                    pairs.push(Pair::new(open, close, corruption, completion));
+                }
+                _ => return Err(error("expected open, close, corruption score and completion score")),
+            }
+        }
+
+        if pairs.is_empty() {
+            return Err(ConfigError { line: config.lines().count(), message: "no delimiter pairs defined".to_string() });
+        }
+
+        Self::new(pairs, other).map_err(|message| ConfigError { line: config.lines().count(), message })
+    }
+
+    fn token_at(&self, prev: Option<char>, chars: &VecDeque<char>) -> Option<(usize, usize, bool)> {
+        // Returns pair number, length and open flag of the delimiter at the start of chars.
+        // Delimiters starting or ending with a letter or digit can't be part of a longer word
+        let word_break = |a: Option<char>, b: Option<&char>| !(a.is_some_and(is_word) && b.is_some_and(|&b| is_word(b)));
+
+        self.tokens.iter()
+            .find(|(token, _, _)| {
+                token.len() <= chars.len() && token.iter().zip(chars).all(|(a, b)| a == b) &&
+                    word_break(prev, token.first()) && word_break(token.last().copied(), chars.get(token.len()))
+            })
+            .map(|(token, pair, open)| (*pair, token.len(), *open))
+    }
+
+    fn max_token_len(&self) -> usize {
+        self.tokens.first().map_or(1, |(token, _, _)| token.len())
+    }
+
+    pub fn check_line(&self, line: &[char]) -> LineReport {
+        let mut checker = LineChecker::new(self);
+
+        for &c in line {
+            checker.push(c);
+        }
+
+        checker.finish()
+    }
+
+    pub fn repair_line(&self, line: &[char]) -> String {
+        match self.check_line(line) {
+            LineReport::Valid => line.iter().collect(),
+            LineReport::Incomplete { completion } => line.iter().collect::<String>() + &completion.concat(),
+            LineReport::Corrupted { column, .. } | LineReport::Invalid { column, .. } => {
+                // Truncate at the error and complete what is left
+                self.repair_line(&line[..column])
+            }
+        }
+    }
+
+    fn pair_for_close(&self, close: &str) -> Result<&Pair, String> {
+        self.pairs.iter().find(|p| p.close == close).ok_or_else(|| format!("Close delimiter {} not found", close))
+    }
+
+    pub fn corruption_score(&self, report: &LineReport) -> Result<Option<usize>, String> {
+        match report {
+            LineReport::Corrupted { found, .. } => Ok(Some(self.pair_for_close(found)?.corruption_score)),
+            _ => Ok(None)
+        }
+    }
+
+    pub fn completion_score(&self, report: &LineReport) -> Result<Option<usize>, String> {
+        match report {
+            LineReport::Incomplete { completion } => completion.iter().try_fold(0, |score: usize, close| {
+                // Saturate rather than overflow on very deeply nested lines
+                Ok(score.saturating_mul(5).saturating_add(self.pair_for_close(close)?.completion_score))
+            }).map(Some),
+            _ => Ok(None)
+        }
+    }
+
+}
+
+pub struct LineChecker<'a> {
+    delimiters: &'a Delimiters,
+    // Open pair numbers, a byte each so very long lines stay small
+    stack: Vec<u8>,
+    // Characters not yet matched against the delimiters
+    pending: VecDeque<char>,
+    // Column of the first pending character
+    column: usize,
+    // Character before the first pending character
+    prev: Option<char>,
+    error: Option<LineReport>,
+}
+
+impl<'a> LineChecker<'a> {
+
+    pub fn new(delimiters: &'a Delimiters) -> Self {
+        LineChecker {
+            delimiters,
+            stack: Vec::new(),
+            pending: VecDeque::new(),
+            column: 0,
+            prev: None,
+            error: None,
+        }
+    }
+
+    pub fn push(&mut self, c: char) {
+        if self.error.is_some() {
+            // Skip the rest of the line
+            return;
+        }
+
+        self.pending.push_back(c);
+
+        // Hold back the longest delimiter and the character after it
+        while self.error.is_none() && self.pending.len() > self.delimiters.max_token_len() {
+            self.process();
+        }
+    }
+
+    pub fn finish(mut self) -> LineReport {
+        self.flush();
+
+        if let Some(error) = self.error {
+            error
+        } else if self.stack.is_empty() {
+            LineReport::Valid
+        } else {
+            LineReport::Incomplete {
+                completion: self.stack.iter().rev().map(|&p| self.delimiters.pairs[p as usize].close.clone()).collect(),
+            }
+        }
+    }
+
+    pub fn finish_scores(mut self) -> Result<(Option<usize>, Option<usize>), String> {
+        // Corruption and completion scores straight from the open pairs, without building the
+        // completion
+        self.flush();
+
+        let pairs = &self.delimiters.pairs;
+
+        match &self.error {
+            Some(error) => Ok((self.delimiters.corruption_score(error)?, None)),
+            None if self.stack.is_empty() => Ok((None, None)),
+            None => Ok((None, Some(self.stack.iter().rev().fold(0, |score: usize, &p| {
+                score.saturating_mul(5).saturating_add(pairs[p as usize].completion_score)
+            })))),
+        }
+    }
+
+    fn flush(&mut self) {
+        while self.error.is_none() && !self.pending.is_empty() {
+            self.process();
+        }
+    }
+
+    fn process(&mut self) {
+        let delimiters = self.delimiters;
+
+        let Some((pair, len, open)) = delimiters.token_at(self.prev, &self.pending) else {
+            let found = self.pending.pop_front().unwrap();
+
+            self.prev = Some(found);
+
+            if delimiters.other == OtherChars::Error {
+                self.error = Some(LineReport::Invalid { column: self.column, found });
+            }
+
+            self.column += 1;
+            return
+        };
+
+        if open {
+            self.stack.push(pair as u8);
+        } else {
+            match self.stack.pop().map(usize::from) {
+                Some(open) if open == pair => (),
+                open => {
+                    self.error = Some(LineReport::Corrupted {
+                        column: self.column,
+                        expected: open.map(|o| delimiters.pairs[o].close.clone()),
+                        found: delimiters.pairs[pair].close.clone(),
+                    });
+
+                    return
+                }
+            }
+        }
+
+        self.prev = self.pending.drain(..len).next_back();
+        self.column += len;
+    }
+
+}
+
+fn is_word(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+#[cfg(test)]
+fn chars(s: &str) -> Vec<char> {
+    s.chars().collect()
+}
+
+#[test]
+fn test_reports() {
+    let delimiters = Delimiters::default();
+
+    assert_eq!(delimiters.check_line(&chars("[<>({}){}[([])<>]]")), LineReport::Valid);
+
+    assert_eq!(delimiters.check_line(&chars("{([(<{}[<>[]}>{[]{[(<()>")), LineReport::Corrupted {
+        column: 12,
+        expected: Some("]".to_string()),
+        found: "}".to_string(),
+    });
+
+    assert_eq!(delimiters.check_line(&chars("())")), LineReport::Corrupted {
+        column: 2,
+        expected: None,
+        found: ")".to_string(),
+    });
+
+    assert_eq!(delimiters.check_line(&chars("(a)")), LineReport::Invalid { column: 1, found: 'a' });
+
+    let report = delimiters.check_line(&chars("[({(<(())[]>[[{[]{<()<>>"));
+    assert_eq!(report.to_string(), "incomplete, complete with }}]])})]");
+    assert_eq!(delimiters.completion_score(&report), Ok(Some(288957)));
+}
+
+#[test]
+fn test_repair() {
+    let delimiters = Delimiters::default();
+
+    assert_eq!(delimiters.repair_line(&chars("[({(<(())[]>[[{[]{<()<>>")), "[({(<(())[]>[[{[]{<()<>>}}]])})]");
+    assert_eq!(delimiters.repair_line(&chars("{([(<{}[<>[]}>{[]{[(<()>")), "{([(<{}[<>[]]>)])}");
+    assert_eq!(delimiters.repair_line(&chars("())")), "()");
+    assert_eq!(delimiters.repair_line(&chars("((x))")), "(())");
+
+    for line in ["{([(<{}[<>[]}>{[]{[(<()>", "[[<[([]))<([[{}[[()]]]", "())"] {
+        assert_eq!(delimiters.check_line(&chars(&delimiters.repair_line(&chars(line)))), LineReport::Valid);
+    }
+}
+
+#[test]
+fn test_config() {
+    let delimiters = Delimiters::parse("\
+# C style comments and blocks
+/* */ 10 1
+{ } 5 2
+begin end 7 3
+other ignore").unwrap();
+
+    assert_eq!(delimiters.check_line(&chars("{ /* x */ }")), LineReport::Valid);
+    assert_eq!(delimiters.check_line(&chars("begin { x = 1; } end")), LineReport::Valid);
+
+    let report = delimiters.check_line(&chars("begin /* */ }"));
+    assert_eq!(report, LineReport::Corrupted {
+        column: 12,
+        expected: Some("end".to_string()),
+        found: "}".to_string(),
+    });
+    assert_eq!(delimiters.corruption_score(&report), Ok(Some(5)));
+
+    let report = delimiters.check_line(&chars("begin { /*"));
+    assert_eq!(report.to_string(), "incomplete, complete with */}end");
+    assert_eq!(delimiters.completion_score(&report), Ok(Some((5 + 2) * 5 + 3)));
+    assert_eq!(delimiters.repair_line(&chars("begin { /* } */")), "begin { /* */}end");
+
+    assert_eq!(Delimiters::parse("( ) 1 2\n[ ] x 2").unwrap_err(), ConfigError {
+        line: 2,
+        message: "invalid corruption score".to_string(),
+    });
+    assert_eq!(Delimiters::parse("( ) 1 2\nother maybe").unwrap_err().line, 2);
+    assert!(Delimiters::parse("# nothing\n").is_err());
+
+    // Keywords only match whole words
+    assert_eq!(delimiters.check_line(&chars("begin beginning endless end")), LineReport::Valid);
+    assert_eq!(delimiters.check_line(&chars("begin x_end")).to_string(), "incomplete, complete with end");
+    assert_eq!(delimiters.check_line(&chars("{begin}end")), LineReport::Corrupted {
+        column: 6,
+        expected: Some("end".to_string()),
+        found: "}".to_string(),
+    });
+
+    // Delimiters shared between pairs are ambiguous
+    assert_eq!(Delimiters::parse("( ) 1 2\n[ ) 3 4").unwrap_err(), ConfigError {
+        line: 2,
+        message: "delimiter ) is used more than once".to_string(),
+    });
+    assert!(Delimiters::parse("( ) 1 2\n) ( 3 4").is_err());
+    assert!(Delimiters::new(vec![Pair::new("(", ")", 1, 2), Pair::new("<", ")", 3, 4)], OtherChars::Error).is_err());
+
+    let many: Vec<Pair> = (0..=MAX_PAIRS).map(|i| Pair::new(&format!("<{}", i), &format!("{}>", i), 1, 1)).collect();
+    assert_eq!(Delimiters::new(many, OtherChars::Error).err(), Some("at most 256 delimiter pairs are allowed".to_string()));
+
+    let report = LineReport::Corrupted { column: 0, expected: None, found: "?".to_string() };
+    assert_eq!(Delimiters::default().corruption_score(&report), Err("Close delimiter ? not found".to_string()));
+}
@@ -1,128 +1,109 @@
-use std::io::{BufRead, BufReader};
+mod checker;
+mod stream;
+
+use std::io::{BufRead, BufReader, BufWriter, Write};
 use std::error::Error;
 use std::fs::File;
 use memmap2::Mmap;
 
+use checker::{Delimiters, LineReport};
+
 fn main() -> Result<(), Box<dyn Error>> {
-    // Load the input file
-    let code = load_input("input10.txt")?;
+    // Process options
+    let mut input = "input10.txt".to_string();
+    let mut fix_file = None;
+    let mut show_report = false;
+    let mut streaming = false;
+    let mut delimiters = Delimiters::default();
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--input" => input = args.next().ok_or("--input expects a file name")?,
+            "--fix" => fix_file = Some(args.next().ok_or("--fix expects a file name")?),
+            "--report" => show_report = true,
+            "--stream" => streaming = true,
+            "--delimiters" => {
+                let file = args.next().ok_or("--delimiters expects a file name")?;
+
+                delimiters = Delimiters::parse(&std::fs::read_to_string(&file)?)
+                    .map_err(|e| format!("{}: {}", file, e))?;
+            }
+            _ => return Err(format!("Unrecognised argument '{}'", arg).into()),
+        }
+    }
 
-    // Run parts
-    let (score1, score2) = score_syntax_errors(&code);
+    if streaming {
+        if fix_file.is_some() {
+            return Err("--fix can't be used with --stream".into());
+        }
 
-    println!("Part 1: Syntax checker score: {}", score1);
-    println!("Part 2: Auto-correct score: {}", score2);
+        // Check line by line without loading the file
+        let mut print = |line_no, report: &LineReport| println!("Line {}: {}", line_no, report);
 
-    Ok(())
-}
+        let summary = stream::check_stream(&delimiters, BufReader::new(File::open(&input)?), if show_report { Some(&mut print) } else { None })?;
 
-#[derive(Debug, PartialEq, Eq)]
-enum Token {
-    OpenRound,
-    OpenSquare,
-    OpenCurly,
-    OpenAngle,
-    ClosedRound,
-    ClosedSquare,
-    ClosedCurly,
-    ClosedAngle,
-}
+        println!("Part 1: Syntax checker score: {}", summary.corruption_score);
+        println!("Part 2: Auto-correct score: {}", summary.completion_median.unwrap_or(0));
 
-impl Token {
+        return Ok(());
+    }
 
-    fn is_open(&self) -> bool {
-        matches!(self, Token::OpenRound | Token::OpenSquare | Token::OpenCurly | Token::OpenAngle)
+    // Load the input file
+    let code = load_input(&input)?;
+
+    if show_report {
+        report(&delimiters, &code);
     }
 
-    fn opposite(&self) -> Token {
-        match self {
-            Token::OpenRound => Token::ClosedRound,
-            Token::OpenSquare => Token::ClosedSquare,
-            Token::OpenCurly => Token::ClosedCurly,
-            Token::OpenAngle => Token::ClosedAngle,
-            Token::ClosedRound => Token::OpenRound,
-            Token::ClosedSquare => Token::OpenSquare,
-            Token::ClosedCurly => Token::OpenCurly,
-            Token::ClosedAngle => Token::OpenAngle,
-        }
+    if let Some(fix_file) = fix_file {
+        fix(&delimiters, &code, &fix_file)?;
     }
 
-}
+    // Run parts
+    let (score1, score2) = score_syntax_errors(&delimiters, &code)?;
 
-impl From<char> for Token {
-
-    fn from(c: char) -> Self {
-        match c {
-            '(' => Token::OpenRound,
-            '[' => Token::OpenSquare,
-            '{' => Token::OpenCurly,
-            '<' => Token::OpenAngle,
-            ')' => Token::ClosedRound,
-            ']' => Token::ClosedSquare,
-            '}' => Token::ClosedCurly,
-            '>' => Token::ClosedAngle,
-            _ => panic!("Invalid bracket")
-        }
-    }
+    println!("Part 1: Syntax checker score: {}", score1);
+    println!("Part 2: Auto-correct score: {}", score2);
 
+    Ok(())
 }
 
-fn score_syntax_errors(code: &[Vec<char>]) -> (usize, usize) {
-    let mut score1 = 0;
-    let mut part2_scores = Vec::new();
+fn score_syntax_errors(delimiters: &Delimiters, code: &[Vec<char>]) -> Result<(usize, usize), String> {
+    let reports: Vec<LineReport> = code.iter().map(|line| delimiters.check_line(line)).collect();
 
-    for line in code {
-        let mut stack = Vec::new();
+    let score1 = reports.iter().filter_map(|r| delimiters.corruption_score(r).transpose()).sum::<Result<usize, _>>()?;
 
-        for &token_char in line {
-            let token: Token = token_char.into();
+    let mut part2_scores: Vec<usize> = reports.iter().filter_map(|r| delimiters.completion_score(r).transpose()).collect::<Result<_, _>>()?;
 
-            if token.is_open() {
-                stack.push(token);
-                continue
-            }
+    // Select the median of the completion scores, zero if no lines are incomplete
+    let score2 = if part2_scores.is_empty() {
+        0
+    } else {
+        let mid = (part2_scores.len() - 1) / 2;
 
-            if stack.is_empty() {
-                panic!("Empty stack!")
-            }
+        *part2_scores.select_nth_unstable(mid).1
+    };
 
-            if token.opposite() != *stack.last().unwrap() {
-                score1 += match token {
-                    Token::ClosedRound => 3,
-                    Token::ClosedSquare => 57,
-                    Token::ClosedCurly => 1197,
-                    Token::ClosedAngle => 25137,
-                    _ => panic!("Unexpected char")
-                };
-                
-                stack.clear();
-                break
-            }
+    Ok((score1, score2))
+}
 
-            stack.pop();
-        }
+fn report(delimiters: &Delimiters, code: &[Vec<char>]) {
+    for (line_no, line) in code.iter().enumerate() {
+        println!("Line {}: {}", line_no + 1, delimiters.check_line(line));
+    }
+}
 
-        if !stack.is_empty() {
-            let mut score2 = 0;
-
-            while let Some(e) = stack.pop() {
-                score2 = (score2 * 5) + match e {
-                    Token::OpenRound => 1,
-                    Token::OpenSquare => 2,
-                    Token::OpenCurly => 3,
-                    Token::OpenAngle => 4,
-                    _ => panic!("Invalid bracket in stack")
-                };
-            }
+fn fix(delimiters: &Delimiters, code: &[Vec<char>], filename: &str) -> Result<(), Box<dyn Error>> {
+    let mut writer = BufWriter::new(File::create(filename)?);
 
-            part2_scores.push(score2);
-        }
+    for line in code {
+        writeln!(writer, "{}", delimiters.repair_line(line))?;
     }
 
-    part2_scores.sort_unstable();
-    let score2 = part2_scores[(part2_scores.len() - 1) / 2];
+    writer.flush()?;
 
-    (score1, score2)
+    Ok(())
 }
 
 type ParseResult = Vec<Vec<char>>;
@@ -177,7 +158,7 @@ fn test_scoring() {
     let code = load_buf(test_input.as_bytes()).unwrap();
 
     // Run parts
-    let (score1, score2) = score_syntax_errors(&code);
+    let (score1, score2) = score_syntax_errors(&Delimiters::default(), &code).unwrap();
 
     assert_eq!(score1, 26397, "Part 1 score incorrect");
     assert_eq!(score2, 288957, "Part 2 score incorrect");
@@ -0,0 +1,276 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::io::{self, BufRead};
+
+use super::checker::{Delimiters, LineChecker, LineReport};
+
+#[derive(Default)]
+pub struct RunningMedian {
+    // Lower half of the values, holds the extra value when the count is odd
+    lower: BinaryHeap<usize>,
+    // Upper half of the values
+    upper: BinaryHeap<Reverse<usize>>,
+}
+
+impl RunningMedian {
+
+    pub fn push(&mut self, value: usize) {
+        match self.lower.peek() {
+            Some(&max) if value > max => self.upper.push(Reverse(value)),
+            _ => self.lower.push(value),
+        }
+
+        // Rebalance the halves
+        if self.lower.len() > self.upper.len() + 1 {
+            self.upper.push(Reverse(self.lower.pop().unwrap()));
+        } else if self.upper.len() > self.lower.len() {
+            self.lower.push(self.upper.pop().unwrap().0);
+        }
+    }
+
+    pub fn median(&self) -> Option<usize> {
+        // Lower median for an even count
+        self.lower.peek().copied()
+    }
+
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct StreamSummary {
+    pub lines: usize,
+    pub corruption_score: usize,
+    pub completion_median: Option<usize>,
+}
+
+// Called with the line number and report of each line
+pub type OnLine<'a> = &'a mut dyn FnMut(usize, &LineReport);
+
+pub fn check_stream<R: BufRead>(delimiters: &Delimiters, mut reader: R, mut on_line: Option<OnLine>) -> io::Result<StreamSummary> {
+    // Reports are only built for the callback, scoring works from the open pairs alone
+    let mut summary = StreamSummary::default();
+    let mut median = RunningMedian::default();
+
+    let mut checker = LineChecker::new(delimiters);
+    let mut line_len = 0;
+
+    // Carriage return held back in case it ends the line
+    let mut cr = false;
+
+    // Bytes of a UTF-8 sequence split across buffer fills
+    let mut partial: Vec<u8> = Vec::with_capacity(4);
+
+    let mut end_line = |checker: LineChecker, summary: &mut StreamSummary| -> io::Result<()> {
+        summary.lines += 1;
+
+        let invalid = |message| io::Error::new(io::ErrorKind::InvalidData, message);
+
+        let (corruption, completion) = match on_line.as_mut() {
+            Some(on_line) => {
+                let report = checker.finish();
+
+                on_line(summary.lines, &report);
+
+                (delimiters.corruption_score(&report).map_err(invalid)?, delimiters.completion_score(&report).map_err(invalid)?)
+            }
+            None => checker.finish_scores().map_err(invalid)?,
+        };
+
+        if let Some(score) = corruption {
+            summary.corruption_score += score;
+        }
+
+        if let Some(score) = completion {
+            median.push(score);
+        }
+
+        Ok(())
+    };
+
+    loop {
+        let buf = reader.fill_buf()?;
+
+        if buf.is_empty() {
+            break;
+        }
+
+        let consumed = buf.len();
+
+        // Complete any sequence split over the previous fill
+        let mut bytes = buf;
+        let joined: Vec<u8>;
+
+        if !partial.is_empty() {
+            let take = bytes.len().min(4 - partial.len());
+
+            joined = partial.iter().chain(&bytes[..take]).copied().collect();
+
+            let valid = match std::str::from_utf8(&joined) {
+                Ok(s) => s.len(),
+                Err(e) if e.error_len().is_none() => e.valid_up_to(),
+                Err(_) => return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid UTF-8 in input")),
+            };
+
+            if valid == 0 {
+                // Still incomplete
+                partial = joined;
+                reader.consume(consumed);
+                continue;
+            }
+
+            let c = std::str::from_utf8(&joined[..valid]).unwrap().chars().next().unwrap();
+
+            bytes = &bytes[c.len_utf8() - partial.len()..];
+            partial.clear();
+
+            if std::mem::take(&mut cr) {
+                checker.push('\r');
+                line_len += 1;
+            }
+
+            checker.push(c);
+            line_len += 1;
+        }
+
+        let (text, rest) = match std::str::from_utf8(bytes) {
+            Ok(text) => (text, &[][..]),
+            Err(e) if e.error_len().is_none() => {
+                let (valid, rest) = bytes.split_at(e.valid_up_to());
+
+                (std::str::from_utf8(valid).unwrap(), rest)
+            }
+            Err(_) => return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid UTF-8 in input")),
+        };
+
+        for c in text.chars() {
+            // Only a carriage return before a newline is part of the line ending
+            if std::mem::take(&mut cr) && c != '\n' {
+                checker.push('\r');
+                line_len += 1;
+            }
+
+            match c {
+                '\n' => {
+                    // Skip empty lines as load_buf does
+                    if line_len > 0 {
+                        end_line(std::mem::replace(&mut checker, LineChecker::new(delimiters)), &mut summary)?;
+                    }
+
+                    line_len = 0;
+                }
+                '\r' => cr = true,
+                _ => {
+                    checker.push(c);
+                    line_len += 1;
+                }
+            }
+        }
+
+        partial.extend_from_slice(rest);
+        reader.consume(consumed);
+    }
+
+    if !partial.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "truncated UTF-8 at end of input"));
+    }
+
+    // A carriage return at the end of the input is kept, as BufRead::lines does
+    if cr {
+        checker.push('\r');
+        line_len += 1;
+    }
+
+    if line_len > 0 {
+        end_line(checker, &mut summary)?;
+    }
+
+    summary.completion_median = median.median();
+
+    Ok(summary)
+}
+
+#[test]
+fn test_running_median() {
+    let mut median = RunningMedian::default();
+
+    assert_eq!(median.median(), None);
+
+    for (value, expected) in [(5, 5), (1, 1), (9, 5), (7, 5), (3, 5), (2, 3), (8, 5)] {
+        median.push(value);
+        assert_eq!(median.median(), Some(expected));
+    }
+}
+
+#[test]
+fn test_stream() {
+    use std::io::BufReader;
+
+    let test_input = "\
+[({(<(())[]>[[{[]{<()<>>
+[(()[<>])]({[<{<<[]>>(
+{([(<{}[<>[]}>{[]{[(<()>
+(((({<>}<{<{<>}{[]{[]{}
+[[<[([]))<([[{}[[()]]]
+[{[{({}]{}}([{[{{{}}([]
+{<[[]]>}<{[{[{[]{()[[[]
+[<(<(<(<{}))><([]([]()
+<{([([[(<>()){}]>(<<{{
+<{([{{}}[<[[[<>{}]]]>[]]";
+
+    let delimiters = Delimiters::default();
+
+    // Tiny buffer to exercise lines spanning many fills
+    let mut reports = Vec::new();
+    let summary = check_stream(&delimiters, BufReader::with_capacity(3, test_input.as_bytes()), Some(&mut |line, report| {
+        reports.push((line, report.to_string()));
+    })).unwrap();
+
+    assert_eq!(summary, StreamSummary { lines: 10, corruption_score: 26397, completion_median: Some(288957) });
+    assert_eq!(reports[2], (3, "corrupted at column 13, expected ] but found }".to_string()));
+
+    // Scoring without reports agrees
+    assert_eq!(check_stream(&delimiters, BufReader::with_capacity(3, test_input.as_bytes()), None).unwrap(), summary);
+
+    // Multi-byte delimiters split across fills
+    let delimiters = Delimiters::parse("« » 1 1\n/* */ 2 2").unwrap();
+
+    let summary = check_stream(&delimiters, BufReader::with_capacity(1, "«/*»\r\n\n«/**/»«".as_bytes()), None).unwrap();
+    assert_eq!(summary, StreamSummary { lines: 2, corruption_score: 1, completion_median: Some(1) });
+
+    assert!(check_stream(&delimiters, BufReader::new(&b"\xff"[..]), None).is_err());
+
+    // Carriage returns within a line agree with checking the loaded lines
+    let delimiters = Delimiters::default();
+    let input = "(\r)\r\n[]\r\n\r\n<\r\r\n{}\r";
+
+    for capacity in [1, 2, 64] {
+        let mut reports = Vec::new();
+
+        check_stream(&delimiters, BufReader::with_capacity(capacity, input.as_bytes()), Some(&mut |_, report| reports.push(report.to_string()))).unwrap();
+
+        let expected: Vec<String> = crate::load_buf(input.as_bytes()).unwrap().iter()
+            .map(|line| delimiters.check_line(line).to_string())
+            .collect();
+
+        assert_eq!(reports, expected);
+        assert_eq!(reports[0], "invalid character \r at column 2");
+    }
+}
+
+#[test]
+fn test_long_line() {
+    use std::io::Read;
+
+    // A line far larger than the read buffer, never held in memory as a whole
+    let depth = 1_000_000;
+    let reader = std::io::repeat(b'(').take(depth).chain(std::io::repeat(b')').take(depth));
+
+    let summary = check_stream(&Delimiters::default(), std::io::BufReader::new(reader), None).unwrap();
+
+    assert_eq!(summary, StreamSummary { lines: 1, corruption_score: 0, completion_median: None });
+
+    // Incomplete lines are scored from a byte per open delimiter
+    let reader = std::io::repeat(b'<').take(depth).chain(std::io::repeat(b'(').take(2));
+    let summary = check_stream(&Delimiters::default(), std::io::BufReader::new(reader), None).unwrap();
+
+    assert_eq!(summary.completion_median, Some(usize::MAX));
+}
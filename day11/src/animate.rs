@@ -0,0 +1,401 @@
+use std::borrow::Cow;
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, Write};
+use std::thread;
+use std::time::Duration;
+
+use gif::{Encoder, Frame, Repeat};
+
+use super::automaton::{Octopuses, Outcome};
+
+// Palette index of flashing octopuses
+const FLASH: u8 = 10;
+
+// Palette index of the frame number overlay
+const TEXT: u8 = 11;
+
+// Delay in 1/100ths of a second to hold the final frame
+const FINAL_DELAY: u16 = 300;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Palette {
+    Pink,
+    Grey,
+    Ocean,
+    Fire,
+}
+
+impl Palette {
+
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "pink" => Ok(Palette::Pink),
+            "grey" => Ok(Palette::Grey),
+            "ocean" => Ok(Palette::Ocean),
+            "fire" => Ok(Palette::Fire),
+            _ => Err(format!("Unknown palette '{}', expected pink, grey, ocean or fire", name)),
+        }
+    }
+
+    fn colours(&self) -> [[u8; 3]; 12] {
+        // Energy levels 0 to 9, then flash and text colours
+        match self {
+            Palette::Pink => [
+                [0x59, 0x0d, 0x22], [0x80, 0x0f, 0x2f], [0xa4, 0x13, 0x3c], [0xc9, 0x18, 0x4a],
+                [0xff, 0x4d, 0x6d], [0xff, 0x75, 0x8f], [0xff, 0x8f, 0xa3], [0xff, 0xb3, 0xc1],
+                [0xff, 0xcc, 0xd5], [0xff, 0xf0, 0xf3], [0xff, 0xff, 0x00], [0xff, 0xff, 0xff],
+            ],
+            Palette::Grey => {
+                let mut colours = [[0; 3]; 12];
+
+                for (level, colour) in colours.iter_mut().take(10).enumerate() {
+                    *colour = [(0x10 + level * 0x18) as u8; 3];
+                }
+
+                colours[FLASH as usize] = [0xff, 0xff, 0xff];
+                colours[TEXT as usize] = [0xff, 0x40, 0x40];
+                colours
+            }
+            Palette::Ocean => [
+                [0x01, 0x2a, 0x4a], [0x01, 0x3a, 0x63], [0x01, 0x49, 0x7c], [0x01, 0x4f, 0x86],
+                [0x2a, 0x6f, 0x97], [0x2c, 0x7d, 0xa0], [0x46, 0x8f, 0xaf], [0x61, 0xa5, 0xc2],
+                [0x89, 0xc2, 0xd9], [0xa9, 0xd6, 0xe5], [0xff, 0xff, 0xff], [0xff, 0xd0, 0x00],
+            ],
+            Palette::Fire => [
+                [0x10, 0x00, 0x00], [0x40, 0x00, 0x00], [0x70, 0x08, 0x00], [0xa0, 0x18, 0x00],
+                [0xd0, 0x30, 0x00], [0xf0, 0x50, 0x00], [0xff, 0x78, 0x00], [0xff, 0xa0, 0x10],
+                [0xff, 0xc8, 0x40], [0xff, 0xe8, 0x80], [0xff, 0xff, 0xff], [0x40, 0xc0, 0xff],
+            ],
+        }
+    }
+
+}
+
+#[derive(Debug, Clone)]
+pub struct Animation {
+    // First and last steps to show, the last defaults to the synchronising step
+    pub from: usize,
+    pub to: Option<usize>,
+    pub scale: usize,
+    pub palette: Palette,
+    // Delay between steps in 1/100ths of a second
+    pub delay: u16,
+    pub highlight: bool,
+    pub frame_numbers: bool,
+    // Times to play the animation, zero to loop forever
+    pub loops: u16,
+}
+
+impl Default for Animation {
+    fn default() -> Self {
+        Animation {
+            from: 0,
+            to: None,
+            scale: 50,
+            palette: Palette::Pink,
+            delay: 10,
+            highlight: true,
+            frame_numbers: false,
+            loops: 0,
+        }
+    }
+}
+
+struct AnimFrame {
+    step: usize,
+    // Palette index for each octopus
+    cells: Vec<u8>,
+    delay: u16,
+}
+
+impl Animation {
+
+    fn last_step(&self, octopuses: &Octopuses) -> usize {
+        self.to.unwrap_or_else(|| match octopuses.run_until_sync() {
+            Outcome::Synchronised(step) => step,
+            Outcome::Cycle { pre_period, period } => pre_period + period,
+        })
+    }
+
+    fn frames<'a>(&'a self, octopuses: &Octopuses) -> impl Iterator<Item = AnimFrame> + 'a {
+        // Frames are produced as they are needed rather than held in memory
+        let last = self.last_step(octopuses);
+        let mut octopuses = octopuses.clone();
+
+        let delay = move |step: usize| if step >= last { self.delay.max(FINAL_DELAY) } else { self.delay };
+
+        // Skip to the first step
+        for _ in 0..self.from {
+            octopuses.step();
+        }
+
+        let first = AnimFrame { step: self.from, cells: frame_cells(&octopuses, false), delay: delay(self.from) };
+
+        let rest = (self.from + 1..=last).flat_map(move |step| {
+            octopuses.step();
+
+            // Show flashing octopuses briefly
+            let flashing = self.highlight.then(|| AnimFrame { step, cells: frame_cells(&octopuses, true), delay: (self.delay / 10).max(1) });
+
+            flashing.into_iter().chain([AnimFrame { step, cells: frame_cells(&octopuses, false), delay: delay(step) }])
+        });
+
+        std::iter::once(first).chain(rest)
+    }
+
+    pub fn write_gif(&self, octopuses: &Octopuses, filename: &str) -> Result<(), Box<dyn Error>> {
+        let width = octopuses.width * self.scale;
+        let height = octopuses.height * self.scale;
+
+        if width > u16::MAX as usize || height > u16::MAX as usize {
+            return Err("Animation is too large for a GIF".into());
+        }
+
+        self.encode_gif(octopuses, File::create(filename)?)
+    }
+
+    fn encode_gif<W: Write>(&self, octopuses: &Octopuses, image: W) -> Result<(), Box<dyn Error>> {
+        let width = octopuses.width * self.scale;
+        let height = octopuses.height * self.scale;
+
+        let colour_map: Vec<u8> = self.palette.colours().iter().flatten().copied().collect();
+
+        let mut encoder = Encoder::new(image, width as u16, height as u16, &colour_map)?;
+
+        // The NETSCAPE2.0 loop count is the number of times to repeat after the first play, with
+        // zero meaning forever. Playing once needs no extension at all
+        match self.loops {
+            0 => encoder.set_repeat(Repeat::Infinite)?,
+            1 => (),
+            n => encoder.set_repeat(Repeat::Finite(n - 1))?,
+        }
+
+        for frame in self.frames(octopuses) {
+            let mut pixels = scale_cells(&frame.cells, octopuses.width, self.scale);
+
+            if self.frame_numbers {
+                draw_number(&mut pixels, width, frame.step, (self.scale / 10).max(1));
+            }
+
+            encoder.write_frame(&Frame {
+                width: width as u16,
+                height: height as u16,
+                delay: frame.delay,
+                buffer: Cow::Borrowed(&pixels),
+                ..Frame::default()
+            })?;
+        }
+
+        Ok(())
+    }
+
+    pub fn play_ascii<W: Write>(&self, octopuses: &Octopuses, out: &mut W, sleep: bool) -> io::Result<()> {
+        let colours = self.palette.colours();
+        let mut played = 0;
+
+        // Work out the last step once rather than on every loop
+        let animation = Animation { to: Some(self.last_step(octopuses)), ..self.clone() };
+
+        loop {
+            for frame in animation.frames(octopuses) {
+                // Clear screen and home the cursor
+                write!(out, "\x1b[2J\x1b[H")?;
+
+                if self.frame_numbers {
+                    writeln!(out, "Step {}", frame.step)?;
+                }
+
+                for row in frame.cells.chunks(octopuses.width) {
+                    let mut line = String::new();
+
+                    for &cell in row {
+                        let [r, g, b] = colours[cell as usize];
+                        let c = if cell == FLASH { '*' } else { (b'0' + cell) as char };
+
+                        line += &format!("\x1b[38;2;{};{};{}m", r, g, b);
+                        line.extend(std::iter::repeat_n(c, self.scale));
+                    }
+
+                    line += "\x1b[0m\n";
+
+                    for _ in 0..self.scale {
+                        out.write_all(line.as_bytes())?;
+                    }
+                }
+
+                out.flush()?;
+
+                if sleep {
+                    thread::sleep(Duration::from_millis(frame.delay as u64 * 10));
+                }
+            }
+
+            played += 1;
+
+            if self.loops != 0 && played >= self.loops {
+                break Ok(());
+            }
+        }
+    }
+
+}
+
+fn frame_cells(octopuses: &Octopuses, flashing: bool) -> Vec<u8> {
+    // Palette index for each octopus
+    octopuses.rows().enumerate().flat_map(|(y, row)| {
+        row.iter().enumerate().map(move |(x, &e)| if flashing && octopuses.flashed(x, y) { FLASH } else { e.min(9) })
+    }).collect()
+}
+
+fn scale_cells(cells: &[u8], width: usize, scale: usize) -> Vec<u8> {
+    // Repeat each cell scale times in both directions
+    cells.chunks(width).flat_map(|row| {
+        let scaled: Vec<u8> = row.iter().flat_map(|&c| std::iter::repeat_n(c, scale)).collect();
+
+        std::iter::repeat_n(scaled, scale).flatten()
+    }).collect()
+}
+
+// 3x5 digits, one row per byte, most significant of the low three bits on the left
+const DIGIT_FONT: [[u8; 5]; 10] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b010, 0b010, 0b010],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+];
+
+fn draw_number(pixels: &mut [u8], width: usize, number: usize, size: usize) {
+    // Draw in the top left corner with a one pixel margin, clipped to the image
+    let height = pixels.len() / width;
+
+    for (i, digit) in number.to_string().bytes().enumerate() {
+        let glyph = &DIGIT_FONT[(digit - b'0') as usize];
+
+        for (gy, bits) in glyph.iter().enumerate() {
+            for gx in 0..3 {
+                if bits & (0b100 >> gx) == 0 {
+                    continue;
+                }
+
+                for py in 0..size {
+                    for px in 0..size {
+                        let x = size + (i * 4 + gx) * size + px;
+                        let y = size + gy * size + py;
+
+                        if x < width && y < height {
+                            pixels[y * width + x] = TEXT;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+fn example() -> Octopuses {
+    use super::automaton::Rules;
+
+    let energy: Vec<Vec<u8>> = ["11111", "19991", "19191", "19991", "11111"].iter()
+        .map(|r| r.bytes().map(|b| b - b'0').collect())
+        .collect();
+
+    Octopuses::new(&energy, Rules::default())
+}
+
+#[test]
+fn test_frames() {
+    let animation = Animation { from: 1, to: Some(3), ..Default::default() };
+    let frames: Vec<AnimFrame> = animation.frames(&example()).collect();
+
+    // Initial frame then a flash and normal frame per step
+    assert_eq!(frames.iter().map(|f| f.step).collect::<Vec<_>>(), vec![1, 2, 2, 3, 3]);
+    assert_eq!(frames.iter().map(|f| f.delay).collect::<Vec<_>>(), vec![10, 1, 10, 1, FINAL_DELAY]);
+
+    // Step one flashes have been reset by the start
+    assert!(!frames[0].cells.contains(&FLASH));
+
+    let animation = Animation { highlight: false, to: Some(1), ..Default::default() };
+    let frames: Vec<AnimFrame> = animation.frames(&example()).collect();
+
+    assert_eq!(frames.len(), 2);
+    assert_eq!(frames[1].cells[6], 0);
+}
+
+#[test]
+fn test_gif_loops() {
+    let loop_count = |loops| {
+        let animation = Animation { to: Some(1), scale: 1, loops, ..Default::default() };
+        let mut image = Vec::new();
+
+        animation.encode_gif(&example(), &mut image).unwrap();
+
+        // Repeat count follows the application identifier and sub-block id
+        image.windows(11).position(|w| w == b"NETSCAPE2.0")
+            .map(|pos| u16::from_le_bytes([image[pos + 13], image[pos + 14]]))
+    };
+
+    assert_eq!(loop_count(0), Some(0));
+    assert_eq!(loop_count(1), None);
+    assert_eq!(loop_count(2), Some(1));
+    assert_eq!(loop_count(5), Some(4));
+}
+
+#[test]
+fn test_ascii() {
+    let animation = Animation { to: Some(1), scale: 1, frame_numbers: true, loops: 1, ..Default::default() };
+    let mut out = Vec::new();
+
+    animation.play_ascii(&example(), &mut out, false).unwrap();
+
+    // Strip escape sequences
+    let text = String::from_utf8(out).unwrap();
+    let mut plain = String::new();
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            plain.push(c);
+        }
+    }
+
+    assert_eq!(plain, "\
+Step 0\n11111\n19991\n19191\n19991\n11111\n\
+Step 1\n34543\n4***4\n5***5\n4***4\n34543\n\
+Step 1\n34543\n40004\n50005\n40004\n34543\n");
+}
+
+#[test]
+fn test_overlay() {
+    let mut pixels = vec![0; 20 * 10];
+
+    draw_number(&mut pixels, 20, 17, 1);
+
+    let rows: Vec<String> = pixels.chunks(20)
+        .map(|row| row.iter().map(|&p| if p == TEXT { '#' } else { '.' }).collect())
+        .collect();
+
+    assert_eq!(rows[1], "..#..###............");
+    assert_eq!(rows[3], "..#...#.............");
+    assert_eq!(rows[5], ".###..#.............");
+
+    // Clipped to small images
+    let mut pixels = vec![0; 4 * 4];
+    draw_number(&mut pixels, 4, 8, 2);
+    assert_eq!(pixels.iter().filter(|&&p| p == TEXT).count(), 4);
+
+    assert_eq!(scale_cells(&[1, 2, 3, 4], 2, 2), vec![1, 1, 2, 2, 1, 1, 2, 2, 3, 3, 4, 4, 3, 3, 4, 4]);
+}
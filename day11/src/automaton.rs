@@ -0,0 +1,267 @@
+#[derive(Debug, Clone, PartialEq)]
+pub enum Neighbourhood {
+    Four,
+    Eight,
+    Custom(Vec<(isize, isize)>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Edges {
+    Bounded,
+    Toroidal,
+}
+
+#[derive(Debug, Clone)]
+pub struct Rules {
+    // Octopuses with energy above this flash
+    pub threshold: u8,
+    pub neighbourhood: Neighbourhood,
+    pub edges: Edges,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            threshold: 9,
+            neighbourhood: Neighbourhood::Eight,
+            edges: Edges::Bounded,
+        }
+    }
+}
+
+impl Neighbourhood {
+
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        // 4, 8 or a list of offsets such as "0,-1;-1,0;1,0;0,1"
+        match spec {
+            "4" => Ok(Neighbourhood::Four),
+            "8" => Ok(Neighbourhood::Eight),
+            _ => spec.split(';').map(|offset| {
+                let (dx, dy) = offset.split_once(',').ok_or_else(|| format!("Invalid offset '{}'", offset))?;
+
+                let parse = |s: &str| s.trim().parse::<isize>().map_err(|_| format!("Invalid offset '{}'", offset));
+
+                Ok((parse(dx)?, parse(dy)?))
+            }).collect::<Result<Vec<_>, String>>().map(Neighbourhood::Custom),
+        }
+    }
+
+    fn offsets(&self) -> Vec<(isize, isize)> {
+        match self {
+            Neighbourhood::Four => vec![(0, -1), (-1, 0), (1, 0), (0, 1)],
+            Neighbourhood::Eight => vec![(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)],
+            Neighbourhood::Custom(offsets) => offsets.clone(),
+        }
+    }
+
+}
+
+#[derive(Debug, Clone)]
+pub struct Octopuses {
+    pub width: usize,
+    pub height: usize,
+    pub energy: Vec<u8>,
+    rules: Rules,
+    // Neighbour cell indexes for each cell
+    neighbours: Vec<Vec<usize>>,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Outcome {
+    // All octopuses flash together on this step
+    Synchronised(usize),
+    // State repeats without ever synchronising
+    Cycle { pre_period: usize, period: usize },
+}
+
+impl Octopuses {
+
+    pub fn new(energy: &[Vec<u8>], rules: Rules) -> Self {
+        assert!(rules.threshold < u8::MAX, "Threshold too large");
+
+        let width = energy[0].len();
+        let height = energy.len();
+
+        let offsets = rules.neighbourhood.offsets();
+
+        // Build neighbour lists once
+        let neighbours = (0..height).flat_map(|y| (0..width).map(move |x| (x, y))).map(|(x, y)| {
+            offsets.iter().filter_map(|&(dx, dy)| {
+                let mut nx = x as isize + dx;
+                let mut ny = y as isize + dy;
+
+                if rules.edges == Edges::Toroidal {
+                    nx = nx.rem_euclid(width as isize);
+                    ny = ny.rem_euclid(height as isize);
+                } else if nx < 0 || ny < 0 || nx >= width as isize || ny >= height as isize {
+                    return None;
+                }
+
+                let n = ny as usize * width + nx as usize;
+
+                // Small toroidal grids can wrap back on to the cell itself
+                if n == y * width + x { None } else { Some(n) }
+            }).collect::<Vec<usize>>()
+        }).map(|mut list| {
+            // ...or reach the same neighbour twice
+            list.sort_unstable();
+            list.dedup();
+            list
+        }).collect();
+
+        Octopuses {
+            width,
+            height,
+            energy: energy.iter().flatten().map(|&e| e.min(rules.threshold)).collect(),
+            rules,
+            neighbours,
+        }
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[u8]> {
+        self.energy.chunks(self.width)
+    }
+
+    pub fn flashed(&self, x: usize, y: usize) -> bool {
+        // Every octopus gains energy each step so zero means it has just flashed
+        self.energy[y * self.width + x] == 0
+    }
+
+    pub fn step(&mut self) -> usize {
+        let flash_level = self.rules.threshold + 1;
+        let mut flashers: Vec<usize> = Vec::new();
+
+        // Increase energy
+        for (i, e) in self.energy.iter_mut().enumerate() {
+            *e += 1;
+
+            if *e == flash_level {
+                flashers.push(i);
+            }
+        }
+
+        // Process flashes
+        while let Some(i) = flashers.pop() {
+            for &n in &self.neighbours[i] {
+                let e = &mut self.energy[n];
+
+                if *e < flash_level {
+                    *e += 1;
+
+                    if *e == flash_level {
+                        flashers.push(n);
+                    }
+                }
+            }
+        }
+
+        // Count and reset flashers
+        let mut flash_cnt = 0;
+
+        for e in self.energy.iter_mut() {
+            if *e == flash_level {
+                *e = 0;
+                flash_cnt += 1;
+            }
+        }
+
+        flash_cnt
+    }
+
+    pub fn run_until_sync(&self) -> Outcome {
+        // Brent's cycle detection, checking for synchronisation on each new state
+        let all = self.energy.len();
+
+        let mut power = 1;
+        let mut period = 1;
+        let mut tortoise = self.clone();
+        let mut hare = self.clone();
+        let mut steps = 1;
+
+        if hare.step() == all {
+            return Outcome::Synchronised(steps);
+        }
+
+        while tortoise.energy != hare.energy {
+            if power == period {
+                tortoise.energy.clone_from(&hare.energy);
+                power *= 2;
+                period = 0;
+            }
+
+            steps += 1;
+
+            if hare.step() == all {
+                return Outcome::Synchronised(steps);
+            }
+
+            period += 1;
+        }
+
+        // Find the start of the cycle
+        let mut tortoise = self.clone();
+        let mut hare = self.clone();
+
+        for _ in 0..period {
+            hare.step();
+        }
+
+        let mut pre_period = 0;
+
+        while tortoise.energy != hare.energy {
+            tortoise.step();
+            hare.step();
+            pre_period += 1;
+        }
+
+        Outcome::Cycle { pre_period, period }
+    }
+
+}
+
+#[cfg(test)]
+fn grid(rows: &[&str]) -> Vec<Vec<u8>> {
+    rows.iter().map(|r| r.bytes().map(|b| b - b'0').collect()).collect()
+}
+
+#[test]
+fn test_rules() {
+    // Only orthogonal neighbours gain energy
+    let rules = Rules { neighbourhood: Neighbourhood::Four, ..Default::default() };
+    let mut octopuses = Octopuses::new(&grid(&["000", "090", "000"]), rules);
+
+    assert_eq!(octopuses.step(), 1);
+    assert_eq!(octopuses.energy, vec![1, 2, 1, 2, 0, 2, 1, 2, 1]);
+
+    // Flashes wrap round the edges
+    let rules = Rules { edges: Edges::Toroidal, ..Default::default() };
+    let mut octopuses = Octopuses::new(&grid(&["9000", "0000", "0000", "0000"]), rules);
+
+    assert_eq!(octopuses.step(), 1);
+    assert_eq!(octopuses.energy, vec![0, 2, 1, 2, 2, 2, 1, 2, 1, 1, 1, 1, 2, 2, 1, 2]);
+}
+
+#[test]
+fn test_outcome() {
+    let example = grid(&[
+        "5483143223", "2745854711", "5264556173", "6141336146", "6357385478",
+        "4167524645", "2176841721", "6882881134", "4846848554", "5283751526",
+    ]);
+
+    assert_eq!(Octopuses::new(&example, Rules::default()).run_until_sync(), Outcome::Synchronised(195));
+
+    // A single octopus with nobody to flash with is always synchronised
+    assert_eq!(Octopuses::new(&grid(&["5"]), Rules::default()).run_until_sync(), Outcome::Synchronised(5));
+
+    // Energy only passed rightwards eventually synchronises
+    let rules = Rules { neighbourhood: Neighbourhood::parse("1,0").unwrap(), threshold: 3, ..Default::default() };
+    assert_eq!(Octopuses::new(&grid(&["0123"]), rules).run_until_sync(), Outcome::Synchronised(28));
+
+    // A row of orthogonal neighbours settles into a loop
+    let rules = Rules { neighbourhood: Neighbourhood::Four, threshold: 3, ..Default::default() };
+    assert_eq!(Octopuses::new(&grid(&["0123"]), rules.clone()).run_until_sync(), Outcome::Cycle { pre_period: 4, period: 3 });
+    assert_eq!(Octopuses::new(&grid(&["3021"]), rules).run_until_sync(), Outcome::Cycle { pre_period: 2, period: 3 });
+
+    assert_eq!(Neighbourhood::parse("0,-1;-1,0"), Ok(Neighbourhood::Custom(vec![(0, -1), (-1, 0)])));
+    assert!(Neighbourhood::parse("1").is_err());
+}
@@ -1,203 +1,123 @@
+mod animate;
+mod automaton;
+
 use std::io::{BufRead, BufReader};
 use std::error::Error;
 use std::fs::File;
-use std::borrow::Cow;
 use memmap2::Mmap;
-use gif::{Encoder, Repeat, Frame};
 
-fn main() -> Result<(), Box<dyn Error>> {
-    // Load the input file
-    let energy = load_input("input11.txt")?;
-
-    // Run parts
-    part1(&energy);
-    part2(&energy);
-
-    // Create animated GIF
-    create_gif(&energy);
-
-    Ok(())
-}
+use animate::{Animation, Palette};
+use automaton::{Edges, Neighbourhood, Octopuses, Outcome, Rules};
 
-fn part1(energy: &[Vec<u8>]) {
-    let flashes = count_flashes(energy, 100);
-
-    println!("Part 1: Total flashes: {}", flashes);
-}
-
-fn part2(energy: &[Vec<u8>]) {
-    let all_flash_step = find_sync_step(energy);
-    
-    println!("Part 2: All flash on step: {}", all_flash_step);
-}
-
-fn count_flashes(energy: &[Vec<u8>], steps: usize) -> usize {
-    let mut total = 0;
-
-    let mut loc_energy = energy.to_vec();
-    
-    for _ in 0..steps {
-        total += step(&mut loc_energy, None);
-    }
-
-    total
-}
-
-fn find_sync_step(energy: &[Vec<u8>]) -> usize {
-    let width = energy[0].len();
-    let height = energy.len();
-    let all_flash_cnt = width * height;
-
-    let mut step_cnt = 0;
-    let mut loc_energy = energy.to_vec();
-
-    loop {
-        step_cnt += 1;
-
-        if step(&mut loc_energy, None) == all_flash_cnt {
-            break
-        }
-    }
-
-    step_cnt
-}
-
-fn step(energy: &mut [Vec<u8>], encoder: Option<&mut Encoder<&mut File>>) -> usize {
-    let mut flashers: Vec<(usize, usize)> = Vec::new();
-
-    // Increase energy
-    for (y, line) in energy.iter_mut().enumerate() {
-        for (x, e) in line.iter_mut().enumerate() {
-            *e += 1;
-
-            if *e > 9 {
-                flashers.push((x, y));
+fn main() -> Result<(), Box<dyn Error>> {
+    // Process options
+    let mut input = "input11.txt".to_string();
+    let mut rules = Rules::default();
+    let mut animation = Animation::default();
+    let mut gif_file = "output11.gif".to_string();
+    let mut scale = None;
+    let mut ascii = false;
+    let mut loops = None;
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--input" => input = args.next().ok_or("--input expects a file name")?,
+            "--threshold" => {
+                rules.threshold = args.next().ok_or("--threshold expects an energy level")?.parse()?;
+
+                if rules.threshold == u8::MAX {
+                    return Err("--threshold is too large".into());
+                }
             }
-        }
-    }
-
-    // Process flashes
-    while let Some((x, y)) = flashers.pop() {
-        let (yskip, ytake) = if y == 0 { (0, 2) } else { (y - 1, 3) };
-        let (xskip, xtake) = if x == 0 { (0, 2) } else { (x - 1, 3) };
-
-        for (y, line) in energy.iter_mut().enumerate().skip(yskip).take(ytake) {
-            for (x, e) in line.iter_mut().enumerate().skip(xskip).take(xtake) {
-                if *e < 10 {
-                    *e += 1;
-
-                    if *e > 9 {
-                        flashers.push((x, y));
-                    }
+            "--neighbourhood" => {
+                rules.neighbourhood = Neighbourhood::parse(&args.next().ok_or("--neighbourhood expects 4, 8 or offsets")?)?;
+            }
+            "--toroidal" => rules.edges = Edges::Toroidal,
+            "--gif" => gif_file = args.next().ok_or("--gif expects a file name")?,
+            "--ascii" => ascii = true,
+            "--steps" => {
+                // N..M or N..
+                let range = args.next().ok_or("--steps expects a range such as 10..50")?;
+                let (from, to) = range.split_once("..").ok_or("--steps expects a range such as 10..50")?;
+
+                animation.from = from.parse()?;
+                animation.to = if to.is_empty() { None } else { Some(to.parse()?) };
+
+                if animation.to.is_some_and(|to| to < animation.from) {
+                    return Err("--steps range is empty".into());
                 }
             }
-        }
-    }
-
-    if let Some(encoder) = encoder {
-        write_frame(energy, 1, encoder);
-    }
+            "--scale" => {
+                let factor: usize = args.next().ok_or("--scale expects a factor")?.parse()?;
 
-    // Count and reset flashers
-    let mut flash_cnt = 0;
+                if factor == 0 {
+                    return Err("--scale must be at least 1".into());
+                }
 
-    for line in energy.iter_mut() {
-        for e in line.iter_mut() {
-            if *e > 9 {
-                *e = 0;
-                flash_cnt += 1;
+                scale = Some(factor);
             }
+            "--palette" => animation.palette = Palette::parse(&args.next().ok_or("--palette expects a name")?)?,
+            "--delay" => animation.delay = args.next().ok_or("--delay expects 1/100ths of a second")?.parse()?,
+            "--no-highlight" => animation.highlight = false,
+            "--frame-numbers" => animation.frame_numbers = true,
+            "--loops" => loops = Some(args.next().ok_or("--loops expects a count, 0 for forever")?.parse()?),
+            _ => return Err(format!("Unrecognised argument '{}'", arg).into()),
         }
     }
 
-    flash_cnt
-}
-
-const GIF_COLOUR_MAP: [u8; 33] = [
-    0x59, 0x0d, 0x22,
-    0x80, 0x0f, 0x2f,
-    0xa4, 0x13, 0x3c,
-    0xc9, 0x18, 0x4a,
-    0xff, 0x4d, 0x6d,
-    0xff, 0x75, 0x8f,
-    0xff, 0x8f, 0xa3,
-    0xff, 0xb3, 0xc1,
-    0xff, 0xcc, 0xd5,
-    0xff, 0xf0, 0xf3,
-    0xff, 0xff, 0x00
-];
-
-const GIF_SCALE: usize = 50;
-
-fn create_gif(energy: &[Vec<u8>]) {
-    let width = energy[0].len();
-    let height = energy.len();
-    let all_flash_cnt = width * height;
-
-    let mut image = File::create("output11.gif").unwrap();
-
-    let mut encoder = Encoder::new(&mut image,
-        (width * GIF_SCALE) as u16, (height * GIF_SCALE) as u16,
-        &GIF_COLOUR_MAP).unwrap();
-
-    encoder.set_repeat(Repeat::Infinite).unwrap();
-
-    let mut loc_energy = energy.to_vec();
-
-    write_frame(&loc_energy, 10, &mut encoder);
-
-    loop {
-        if step(&mut loc_energy, Some(&mut encoder)) == all_flash_cnt {
-            break
-        }
+    // Load the input file
+    let energy = load_input(&input)?;
+    let octopuses = Octopuses::new(&energy, rules);
 
-        write_frame(&loc_energy, 10, &mut encoder);
+    // Run parts
+    part1(&octopuses);
+    let outcome = part2(&octopuses);
+
+    // Animate up to the step found in part 2 by default
+    animation.to = animation.to.or(Some(match outcome {
+        Outcome::Synchronised(step) => step,
+        Outcome::Cycle { pre_period, period } => pre_period + period,
+    }));
+
+    if ascii {
+        // Animate in the terminal, one character per octopus and played once by default
+        animation.scale = scale.unwrap_or(1);
+        animation.loops = loops.unwrap_or(1);
+        animation.play_ascii(&octopuses, &mut std::io::stdout(), true)?;
+    } else {
+        // Create animated GIF
+        animation.scale = scale.unwrap_or(animation.scale);
+        animation.loops = loops.unwrap_or(animation.loops);
+        animation.write_gif(&octopuses, &gif_file)?;
     }
 
-    // Write dummy delay frame
-    let frame = Frame {
-        width: 0,
-        height: 0,
-        delay: 300, // 3 seconds
-        ..Frame::default()
-    };
-
-    encoder.write_frame(&frame).unwrap();
+    Ok(())
 }
 
-fn write_frame(energy: &[Vec<u8>], delay: u16, encoder: &mut Encoder<&mut File>) {
-    let width = energy[0].len();
-    let height = energy.len();
+fn part1(octopuses: &Octopuses) {
+    let flashes = count_flashes(octopuses, 100);
 
-    let mut pixels: Vec<u8> = Vec::with_capacity((width * GIF_SCALE) * (height * GIF_SCALE));
-    let mut ptr: usize = 0;
-
-    for line in energy {
-        let start = ptr;
-
-        for &e in line {
-            pixels.resize(ptr + GIF_SCALE, e);
-            ptr += GIF_SCALE;
-        }
+    println!("Part 1: Total flashes: {}", flashes);
+}
 
-        let end = ptr;
+fn part2(octopuses: &Octopuses) -> Outcome {
+    let outcome = octopuses.run_until_sync();
 
-        for _ in 1..GIF_SCALE {
-            pixels.extend_from_within(start..end);
+    match outcome {
+        Outcome::Synchronised(step) => println!("Part 2: All flash on step: {}", step),
+        Outcome::Cycle { pre_period, period } => {
+            println!("Part 2: Never all flash, cycle of period {} starts after step {}", period, pre_period)
         }
-
-        ptr = start + ((width * GIF_SCALE) * GIF_SCALE);
     }
 
-    let frame = Frame {
-        width: (width * GIF_SCALE) as u16,
-        height: (height * GIF_SCALE) as u16,
-        delay,
-        buffer: Cow::Borrowed(&pixels),
-        ..Frame::default()
-    };
+    outcome
+}
+
+fn count_flashes(octopuses: &Octopuses, steps: usize) -> usize {
+    let mut octopuses = octopuses.clone();
 
-    encoder.write_frame(&frame).unwrap();
+    (0..steps).map(|_| octopuses.step()).sum()
 }
 
 type ParseResult = Vec<Vec<u8>>;
@@ -244,29 +164,30 @@ fn test_step() {
 19991
 11111";
 
-    let mut energy = load_buf(energy_input.as_bytes()).unwrap();
+    let energy = load_buf(energy_input.as_bytes()).unwrap();
+    let mut octopuses = Octopuses::new(&energy, Rules::default());
 
-    let flash_cnt = step(&mut energy, None);
+    let flash_cnt = octopuses.step();
 
     assert_eq!(flash_cnt, 9);
 
-    assert_eq!(energy, vec![
-        vec![3, 4, 5, 4, 3],
-        vec![4, 0, 0, 0, 4],
-        vec![5, 0, 0, 0, 5],
-        vec![4, 0, 0, 0, 4],
-        vec![3, 4, 5, 4, 3],
+    assert_eq!(octopuses.rows().collect::<Vec<_>>(), vec![
+        &[3, 4, 5, 4, 3],
+        &[4, 0, 0, 0, 4],
+        &[5, 0, 0, 0, 5],
+        &[4, 0, 0, 0, 4],
+        &[3, 4, 5, 4, 3],
     ]);
 
-    let flash_cnt = step(&mut energy, None);
+    let flash_cnt = octopuses.step();
 
     assert_eq!(flash_cnt, 0);
 
-    assert_eq!(energy, vec![
-        vec![4, 5, 6, 5, 4],
-        vec![5, 1, 1, 1, 5],
-        vec![6, 1, 1, 1, 6],
-        vec![5, 1, 1, 1, 5],
-        vec![4, 5, 6, 5, 4],
+    assert_eq!(octopuses.rows().collect::<Vec<_>>(), vec![
+        &[4, 5, 6, 5, 4],
+        &[5, 1, 1, 1, 5],
+        &[6, 1, 1, 1, 6],
+        &[5, 1, 1, 1, 5],
+        &[4, 5, 6, 5, 4],
     ]);
 }
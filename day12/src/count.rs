@@ -0,0 +1,227 @@
+use std::collections::{BTreeMap, HashMap};
+
+use super::Tree;
+use super::paths::{connected_large_caves, is_small_cave};
+
+type Mask = u64;
+
+type State = (usize, Mask, usize);
+
+pub struct CaveGraph {
+    names: Vec<String>,
+    // Bit for each small cave, zero for large caves
+    bits: Vec<Mask>,
+    // Neighbours of each cave by id
+    adjacent: Vec<Vec<usize>>,
+    start: usize,
+    end: usize,
+}
+
+impl CaveGraph {
+
+    pub fn new(tree: &Tree) -> Result<Self, String> {
+        let mut ids: HashMap<&str, usize> = HashMap::new();
+        let mut names: Vec<String> = Vec::new();
+
+        // Intern cave names, sorted to give stable ids
+        let mut all: Vec<&str> = tree.iter()
+            .flat_map(|(from, tos)| std::iter::once(from.as_str()).chain(tos.iter().map(String::as_str)))
+            .chain(["start", "end"])
+            .collect();
+
+        all.sort_unstable();
+        all.dedup();
+
+        for name in all {
+            ids.insert(name, names.len());
+            names.push(name.to_string());
+        }
+
+        // Allocate bits to small caves
+        let mut next_bit = 0;
+
+        let bits = names.iter().map(|name| {
+            if !is_small_cave(name) {
+                return Ok(0);
+            }
+
+            if next_bit == Mask::BITS {
+                return Err(format!("More than {} small caves", Mask::BITS));
+            }
+
+            next_bit += 1;
+
+            Ok(1 << (next_bit - 1))
+        }).collect::<Result<Vec<Mask>, String>>()?;
+
+        connected_large_caves(tree)?;
+
+        let mut adjacent = vec![Vec::new(); names.len()];
+
+        for from in &names {
+            for to in tree.get(from).into_iter().flatten() {
+                adjacent[ids[from.as_str()]].push(ids[to.as_str()]);
+            }
+        }
+
+        Ok(CaveGraph {
+            start: ids["start"],
+            end: ids["end"],
+            names,
+            bits,
+            adjacent,
+        })
+    }
+
+    pub fn count_paths(&self, revisits: usize) -> u128 {
+        let mut memo = HashMap::new();
+
+        self.count_from(self.start, self.bits[self.start], revisits, &mut memo)
+    }
+
+    pub fn edge_traversals(&self, revisits: usize) -> BTreeMap<(&str, &str), u128> {
+        // Number of times each connection is used across all paths
+        let mut traversals: BTreeMap<(&str, &str), u128> = BTreeMap::new();
+
+        for (from, tos) in self.adjacent.iter().enumerate() {
+            for &to in tos {
+                traversals.insert((&self.names[from], &self.names[to]), 0);
+            }
+        }
+
+        // Visiting a small cave or spending a revisit always moves up two levels, and large caves
+        // sit between small caves, so every move goes to a higher level
+        let level = |&(cave, visited, left): &State| {
+            2 * (visited.count_ones() as usize + revisits - left) + usize::from(self.bits[cave] == 0)
+        };
+
+        let start = (self.start, self.bits[self.start], revisits);
+
+        // Number of ways to reach each state from the start
+        let mut paths_to: HashMap<State, u128> = HashMap::from([(start, 1)]);
+        let mut levels: BTreeMap<usize, Vec<State>> = BTreeMap::from([(level(&start), vec![start])]);
+
+        let mut memo = HashMap::new();
+
+        // Process states in level order so all ways in are counted before moving on
+        while let Some((_, states)) = levels.pop_first() {
+            for state in states {
+                let (cave, visited, left) = state;
+                let before = paths_to[&state];
+
+                for &next in &self.adjacent[cave] {
+                    let bit = self.bits[next];
+
+                    let next_state = if visited & bit == 0 {
+                        (next, visited | bit, left)
+                    } else if left > 0 {
+                        (next, visited, left - 1)
+                    } else {
+                        continue
+                    };
+
+                    let after = self.count_from(next_state.0, next_state.1, next_state.2, &mut memo);
+
+                    if after == 0 {
+                        continue
+                    }
+
+                    *traversals.get_mut(&(self.names[cave].as_str(), self.names[next].as_str())).unwrap() += before * after;
+
+                    if next != self.end {
+                        *paths_to.entry(next_state).or_insert_with(|| {
+                            levels.entry(level(&next_state)).or_default().push(next_state);
+                            0
+                        }) += before;
+                    }
+                }
+            }
+        }
+
+        traversals
+    }
+
+    fn count_from(&self, cave: usize, visited: Mask, revisits: usize, memo: &mut HashMap<State, u128>) -> u128 {
+        if cave == self.end {
+            return 1;
+        }
+
+        if let Some(&count) = memo.get(&(cave, visited, revisits)) {
+            return count;
+        }
+
+        let mut count = 0;
+
+        for &next in &self.adjacent[cave] {
+            let bit = self.bits[next];
+
+            count += if visited & bit == 0 {
+                self.count_from(next, visited | bit, revisits, memo)
+            } else if revisits > 0 {
+                // Spend a revisit on this small cave
+                self.count_from(next, visited, revisits - 1, memo)
+            } else {
+                0
+            };
+        }
+
+        memo.insert((cave, visited, revisits), count);
+
+        count
+    }
+
+}
+
+#[test]
+fn test_count() {
+    use super::load_buf;
+    use super::paths::{PathOptions, Paths};
+
+    let examples = [
+        "start-A\nstart-b\nA-c\nA-b\nb-d\nA-end\nb-end",
+        "dc-end\nHN-start\nstart-kj\ndc-start\ndc-HN\nLN-dc\nHN-end\nkj-sa\nkj-HN\nkj-dc",
+        "fs-end\nhe-DX\nfs-he\nstart-DX\npj-DX\nend-zg\nzg-sl\nzg-pj\npj-he\nRW-he\nfs-DX\npj-RW\nzg-RW\nstart-pj\nhe-WI\nzg-he\npj-fs\nstart-RW",
+    ];
+
+    // Part answers and the largest revisit budget to check against enumeration, which is slow
+    // on the largest example
+    let expected = [(10, 36, 3), (19, 103, 3), (226, 3509, 2)];
+
+    for (example, (part1, part2, max_revisits)) in examples.iter().zip(expected) {
+        let tree = load_buf(example.as_bytes()).unwrap();
+        let graph = CaveGraph::new(&tree).unwrap();
+
+        assert_eq!(graph.count_paths(0), part1);
+        assert_eq!(graph.count_paths(1), part2);
+
+        // Agrees with enumeration for larger budgets
+        for revisits in 2..=max_revisits {
+            let options = PathOptions { revisits, ..Default::default() };
+
+            assert_eq!(graph.count_paths(revisits), Paths::new(&tree, options).unwrap().count() as u128);
+        }
+    }
+
+    // Traversal counts match the enumerated paths
+    let tree = load_buf(examples[0].as_bytes()).unwrap();
+    let graph = CaveGraph::new(&tree).unwrap();
+
+    for revisits in 0..3 {
+        let mut expected: BTreeMap<(&str, &str), u128> = BTreeMap::new();
+
+        for path in Paths::new(&tree, PathOptions { revisits, ..Default::default() }).unwrap() {
+            for pair in path.windows(2) {
+                *expected.entry((pair[0], pair[1])).or_insert(0) += 1;
+            }
+        }
+
+        let traversals = graph.edge_traversals(revisits);
+
+        // d is a dead end unless b can be revisited
+        assert_eq!(traversals[&("b", "d")] == 0, revisits == 0);
+        assert_eq!(traversals.into_iter().filter(|&(_, n)| n > 0).collect::<BTreeMap<_, _>>(), expected);
+    }
+
+    let tree = load_buf("start-A\nA-B\nB-end".as_bytes()).unwrap();
+    assert_eq!(CaveGraph::new(&tree).err(), Some("Large caves A and B are connected".to_string()));
+}
@@ -0,0 +1,340 @@
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::error::Error;
+use std::io::Write;
+
+use super::Tree;
+use super::paths::is_small_cave;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GraphFormat {
+    Native,
+    Dot,
+    Json,
+}
+
+impl GraphFormat {
+
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "native" => Ok(GraphFormat::Native),
+            "dot" => Ok(GraphFormat::Dot),
+            "json" => Ok(GraphFormat::Json),
+            _ => Err(format!("Unknown graph format '{}', expected native, dot or json", name)),
+        }
+    }
+
+    pub fn from_file_name(file: &str) -> Self {
+        match file.rsplit_once('.').map(|(_, ext)| ext) {
+            Some("dot") | Some("gv") => GraphFormat::Dot,
+            Some("json") => GraphFormat::Json,
+            _ => GraphFormat::Native,
+        }
+    }
+
+}
+
+pub fn parse_graph(text: &str, format: GraphFormat) -> Result<Tree, Box<dyn Error>> {
+    let (connections, directed) = match format {
+        GraphFormat::Native => (parse_native(text)?, false),
+        GraphFormat::Dot => parse_dot(text)?,
+        GraphFormat::Json => (parse_json(text)?, false),
+    };
+
+    Ok(tree_from_connections(connections, directed))
+}
+
+pub fn tree_from_connections(connections: Vec<(String, String)>, directed: bool) -> Tree {
+    let mut tree: Tree = HashMap::new();
+    let mut seen: HashSet<(String, String)> = HashSet::new();
+
+    let mut add_tree = |from: &str, to: &str| {
+        // Never go back to the start or leave the end
+        if to != "start" && from != "end" && seen.insert((from.to_string(), to.to_string())) {
+            tree.entry(from.to_string()).or_default().push(to.to_string());
+        }
+    };
+
+    for (a, b) in connections {
+        add_tree(&a, &b);
+
+        if !directed {
+            add_tree(&b, &a);
+        }
+    }
+
+    tree
+}
+
+fn parse_native(text: &str) -> Result<Vec<(String, String)>, String> {
+    text.lines().enumerate().filter(|(_, line)| !line.is_empty()).map(|(line_no, line)| {
+        match line.split_once('-') {
+            Some((a, b)) if !a.is_empty() && !b.is_empty() => Ok((a.to_string(), b.to_string())),
+            _ => Err(format!("Line {}: expected <cave>-<cave>", line_no + 1)),
+        }
+    }).collect()
+}
+
+fn parse_dot(text: &str) -> Result<(Vec<(String, String)>, bool), String> {
+    // Handles the subset of DOT used for cave graphs: edge chains between ids or {id ...} groups.
+    // Attribute lists, node statements and graph attributes are ignored. Edges in a digraph only
+    // go one way, returns the connections and whether they are directed
+    let mut connections = Vec::new();
+
+    // Remove comments and attribute lists
+    let mut clean = String::new();
+    let mut chars = text.chars().peekable();
+    let mut in_string = false;
+    let mut attr_depth = 0;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => in_string = !in_string,
+            '\\' if in_string => {
+                // Skip escaped character
+                chars.next();
+            }
+            _ if in_string && attr_depth == 0 => clean.push(c),
+            _ if in_string => (),
+            '[' => attr_depth += 1,
+            ']' => attr_depth -= 1,
+            '/' if chars.peek() == Some(&'/') => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        clean.push('\n');
+                        break
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+
+                while let Some(c) = chars.next() {
+                    if c == '*' && chars.peek() == Some(&'/') {
+                        chars.next();
+                        break
+                    }
+                }
+            }
+            '#' if clean.ends_with('\n') || clean.is_empty() => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        break
+                    }
+                }
+            }
+            _ if attr_depth == 0 => clean.push(c),
+            _ => (),
+        }
+    }
+
+    if in_string || attr_depth != 0 {
+        return Err("Unterminated string or attribute list".to_string());
+    }
+
+    // Drop the graph header and closing brace
+    let (header, body) = match (clean.find('{'), clean.rfind('}')) {
+        (Some(start), Some(end)) if start < end => (&clean[..start], &clean[start + 1..end]),
+        _ => return Err("Expected graph body in braces".to_string()),
+    };
+
+    let directed = match header.split_whitespace().find(|word| *word != "strict") {
+        Some("digraph") => true,
+        Some("graph") => false,
+        _ => return Err("Expected graph or digraph header".to_string()),
+    };
+
+    let (edge_op, other_op) = if directed { ("->", "--") } else { ("--", "->") };
+
+    for statement in body.split([';', '\n']).map(str::trim).filter(|s| !s.is_empty()) {
+        if statement.contains(other_op) {
+            return Err(format!("Edge operator {} can't be used in a {}: '{}'", other_op,
+                if directed { "digraph" } else { "graph" }, statement));
+        }
+
+        // Split into groups on edge operators
+        let groups: Vec<Vec<&str>> = statement.split(edge_op).map(|group| {
+            group.trim().trim_start_matches('{').trim_end_matches('}').split_whitespace().collect()
+        }).collect();
+
+        if groups.len() < 2 {
+            // Node or attribute statement
+            continue;
+        }
+
+        for pair in groups.windows(2) {
+            if pair[0].is_empty() || pair[1].is_empty() {
+                return Err(format!("Invalid edge statement '{}'", statement));
+            }
+
+            for from in &pair[0] {
+                for to in &pair[1] {
+                    connections.push((from.to_string(), to.to_string()));
+                }
+            }
+        }
+    }
+
+    Ok((connections, directed))
+}
+
+fn parse_json(text: &str) -> Result<Vec<(String, String)>, serde_json::Error> {
+    let adjacency: BTreeMap<String, Vec<String>> = serde_json::from_str(text)?;
+
+    Ok(adjacency.into_iter().flat_map(|(from, tos)| tos.into_iter().map(move |to| (from.clone(), to))).collect())
+}
+
+pub fn bottlenecks(tree: &Tree) -> Vec<&str> {
+    // Caves which every route from start to end must pass through. This is a structural cut
+    // vertex check on the connections only, the small cave visit rules are ignored so a cave
+    // is reported even if no valid path exists at all
+    let reachable_without = |blocked: &str| -> bool {
+        let mut seen: HashSet<&str> = HashSet::from(["start"]);
+        let mut queue: VecDeque<&str> = VecDeque::from(["start"]);
+
+        while let Some(cave) = queue.pop_front() {
+            if cave == "end" {
+                return true;
+            }
+
+            for next in tree.get(cave).into_iter().flatten() {
+                if next != blocked && seen.insert(next) {
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        false
+    };
+
+    let mut caves: Vec<&str> = tree.keys().map(String::as_str).filter(|&c| c != "start" && c != "end").collect();
+    caves.sort_unstable();
+
+    caves.into_iter().filter(|cave| !reachable_without(cave)).collect()
+}
+
+pub fn write_dot<W: Write>(out: &mut W, tree: &Tree, traversals: &BTreeMap<(&str, &str), u128>) -> Result<(), Box<dyn Error>> {
+    writeln!(out, "digraph caves {{")?;
+
+    let bottlenecks = bottlenecks(tree);
+
+    // Number of times each cave is entered
+    let mut visits: BTreeMap<&str, u128> = BTreeMap::new();
+
+    for (&(from, to), &count) in traversals {
+        visits.entry(from).or_insert(0);
+        *visits.entry(to).or_insert(0) += count;
+    }
+
+    // Nothing enters the start so count the paths leaving it
+    let paths = traversals.iter().filter(|((from, _), _)| *from == "start").map(|(_, &count)| count).sum();
+    visits.insert("start", paths);
+
+    // Write nodes
+    for (&cave, &count) in &visits {
+        let mut class = if is_small_cave(cave) { "small" } else { "large" }.to_string();
+        let shape = if is_small_cave(cave) { "ellipse" } else { "box" };
+        let mut extra = "";
+
+        if cave == "start" || cave == "end" {
+            extra = ", color=red";
+        } else if bottlenecks.contains(&cave) {
+            class += " bottleneck";
+            extra = ", style=filled, fillcolor=orange";
+        }
+
+        let attrs = format!("class=\"{}\", shape={}, label=\"{}\\n{}\"{}", class, shape, cave, count, extra);
+
+        writeln!(out, "\t{} [{}];", cave, attrs)?;
+    }
+
+    // Write edges with the number of paths using them
+    let max = traversals.values().copied().max().unwrap_or(0).max(1);
+
+    for (&(from, to), &count) in traversals {
+        if count == 0 {
+            writeln!(out, "\t{} -> {} [label=\"0\", style=dashed, color=grey];", from, to)?;
+        } else {
+            // Scale line width with use
+            let width = 1.0 + 4.0 * (count as f64 / max as f64);
+
+            writeln!(out, "\t{} -> {} [label=\"{}\", penwidth={:.2}];", from, to, count, width)?;
+        }
+    }
+
+    writeln!(out, "}}")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+const EXAMPLE: &str = "start-A\nstart-b\nA-c\nA-b\nb-d\nA-end\nb-end";
+
+#[cfg(test)]
+fn sorted(tree: &Tree) -> BTreeMap<String, Vec<String>> {
+    tree.iter().map(|(from, tos)| {
+        let mut tos = tos.clone();
+        tos.sort();
+        (from.clone(), tos)
+    }).collect()
+}
+
+#[test]
+fn test_formats() {
+    let native = parse_graph(EXAMPLE, GraphFormat::Native).unwrap();
+
+    let dot = parse_graph("\
+// Example caves
+graph caves {
+    start [color=red];
+    start -- {A b};
+    A -- c; A -- b  /* both ways */
+    b -- d
+    A -- end; b -- end [label=\"x]\"];
+}", GraphFormat::Dot).unwrap();
+
+    let json = parse_graph(r#"{"start": ["A", "b"], "A": ["c", "b", "end"], "b": ["d", "end"]}"#, GraphFormat::Json).unwrap();
+
+    assert_eq!(sorted(&dot), sorted(&native));
+    assert_eq!(sorted(&json), sorted(&native));
+
+    assert!(parse_graph("start-A\nstartA", GraphFormat::Native).unwrap_err().to_string().starts_with("Line 2"));
+    assert!(parse_graph("digraph { a -> ; }", GraphFormat::Dot).is_err());
+    assert!(parse_graph("graph { a -> b; }", GraphFormat::Dot).is_err());
+    assert!(parse_graph("digraph { a -- b; }", GraphFormat::Dot).is_err());
+
+    // Digraph edges only go one way
+    let directed = parse_graph("strict digraph { start -> A -> b -> end; A -> end }", GraphFormat::Dot).unwrap();
+    assert_eq!(sorted(&directed), BTreeMap::from([
+        ("start".to_string(), vec!["A".to_string()]),
+        ("A".to_string(), vec!["b".to_string(), "end".to_string()]),
+        ("b".to_string(), vec!["end".to_string()]),
+    ]));
+    assert!(parse_graph("[1, 2]", GraphFormat::Json).is_err());
+
+    assert_eq!(GraphFormat::from_file_name("caves.gv"), GraphFormat::Dot);
+    assert_eq!(GraphFormat::from_file_name("input12.txt"), GraphFormat::Native);
+}
+
+#[test]
+fn test_dot_round_trip() {
+    use super::count::CaveGraph;
+
+    let tree = parse_graph(EXAMPLE, GraphFormat::Native).unwrap();
+    let graph = CaveGraph::new(&tree).unwrap();
+
+    let mut out = Vec::new();
+    write_dot(&mut out, &tree, &graph.edge_traversals(0)).unwrap();
+
+    let dot = String::from_utf8(out).unwrap();
+
+    assert!(dot.contains("\tA [class=\"large\", shape=box, label=\"A\\n17\"];"));
+    assert!(dot.contains("\td [class=\"small\", shape=ellipse, label=\"d\\n0\"];"));
+    assert!(dot.contains("\tb -> d [label=\"0\", style=dashed, color=grey];"));
+    assert!(dot.contains("\tA -> end [label=\"7\", penwidth=5.00];"));
+
+    assert_eq!(sorted(&parse_graph(&dot, GraphFormat::Dot).unwrap()), sorted(&tree));
+
+    // Every path passes through A
+    let tree = parse_graph("start-A\nA-b\nA-c\nb-end\nc-end", GraphFormat::Native).unwrap();
+    assert_eq!(bottlenecks(&tree), vec!["A"]);
+}
@@ -0,0 +1,242 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use super::Tree;
+
+#[derive(Debug, Clone, Default)]
+pub struct PathOptions {
+    // Number of extra visits to small caves allowed
+    pub revisits: usize,
+    // Maximum number of caves in a path, including start and end
+    pub max_len: Option<usize>,
+    pub max_count: Option<usize>,
+    pub must_visit: Vec<String>,
+    pub must_avoid: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PathFormat {
+    Text,
+    Json,
+}
+
+pub struct Paths<'a> {
+    tree: &'a Tree,
+    options: PathOptions,
+    // Caves on the current path
+    path: Vec<&'a str>,
+    // Next choice to try at each depth
+    next_choice: Vec<usize>,
+    // Times each small cave appears on the current path
+    small_visits: HashMap<&'a str, usize>,
+    // Depths at which small caves were revisited
+    revisit_depths: Vec<usize>,
+    found: usize,
+}
+
+pub fn is_small_cave(name: &str) -> bool {
+    name.chars().all(char::is_lowercase)
+}
+
+pub fn connected_large_caves(tree: &Tree) -> Result<(), String> {
+    // Connected large caves could be walked between forever. Report the first pair by name so
+    // the error doesn't depend on hash order
+    let connected = tree.iter()
+        .filter(|(from, _)| !is_small_cave(from))
+        .flat_map(|(from, tos)| tos.iter().filter(|to| !is_small_cave(to)).map(move |to| (from, to)))
+        .min();
+
+    match connected {
+        Some((from, to)) => Err(format!("Large caves {} and {} are connected", from, to)),
+        None => Ok(()),
+    }
+}
+
+impl<'a> Paths<'a> {
+
+    pub fn new(tree: &'a Tree, options: PathOptions) -> Result<Self, String> {
+        // Paths are only finite with connected large caves if their length is limited
+        if options.max_len.is_none() {
+            connected_large_caves(tree).map_err(|e| format!("{}, a maximum length is needed", e))?;
+        }
+
+        Ok(Paths {
+            tree,
+            options,
+            path: vec!["start"],
+            next_choice: vec![0],
+            small_visits: HashMap::new(),
+            revisit_depths: Vec::new(),
+            found: 0,
+        })
+    }
+
+    fn backtrack(&mut self) {
+        let depth = self.path.len() - 1;
+        let cave = self.path.pop().unwrap();
+
+        self.next_choice.pop();
+
+        if let Some(visits) = self.small_visits.get_mut(cave) {
+            *visits -= 1;
+        }
+
+        if self.revisit_depths.last() == Some(&depth) {
+            self.revisit_depths.pop();
+        }
+    }
+
+    fn wanted(&self) -> bool {
+        self.options.must_visit.iter().all(|cave| self.path.contains(&cave.as_str()))
+    }
+
+}
+
+impl<'a> Iterator for Paths<'a> {
+    type Item = Vec<&'a str>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.options.max_count.is_some_and(|max| self.found >= max) {
+            return None;
+        }
+
+        let tree = self.tree;
+
+        while let Some(&pos) = self.path.last() {
+            let depth = self.path.len() - 1;
+            let choices = tree.get(pos).map_or(&[][..], |c| &c[..]);
+
+            let Some(choice) = choices.get(self.next_choice[depth]) else {
+                // No more choices at this depth
+                self.backtrack();
+                continue
+            };
+
+            let choice = choice.as_str();
+            self.next_choice[depth] += 1;
+
+            // Any path through this choice needs at least one more cave to reach the end
+            let min_len = self.path.len() + if choice == "end" { 1 } else { 2 };
+
+            if self.options.max_len.is_some_and(|max| min_len > max) {
+                continue
+            }
+
+            if self.options.must_avoid.iter().any(|cave| cave.as_str() == choice) {
+                continue
+            }
+
+            if choice == "end" {
+                if self.wanted() {
+                    self.found += 1;
+
+                    let mut path = self.path.clone();
+                    path.push(choice);
+
+                    return Some(path);
+                }
+
+                continue
+            }
+
+            if is_small_cave(choice) {
+                if self.small_visits.get(choice).is_some_and(|&visits| visits > 0) {
+                    // Small cave has already been visited
+                    if self.revisit_depths.len() >= self.options.revisits {
+                        continue
+                    }
+
+                    self.revisit_depths.push(depth + 1);
+                }
+
+                *self.small_visits.entry(choice).or_insert(0) += 1;
+            }
+
+            self.path.push(choice);
+            self.next_choice.push(0);
+        }
+
+        None
+    }
+}
+
+pub fn write_paths<'a, W: Write>(out: &mut W, paths: impl Iterator<Item = Vec<&'a str>>, format: PathFormat)
+    -> io::Result<usize> {
+    let mut count = 0;
+
+    for path in paths {
+        match format {
+            PathFormat::Text => writeln!(out, "{}", path.join(","))?,
+            PathFormat::Json => writeln!(out, "{}", serde_json::to_string(&path)?)?,
+        }
+
+        count += 1;
+    }
+
+    Ok(count)
+}
+
+#[cfg(test)]
+fn example() -> Tree {
+    super::load_buf("start-A\nstart-b\nA-c\nA-b\nb-d\nA-end\nb-end".as_bytes()).unwrap()
+}
+
+#[test]
+fn test_enumerate() {
+    let tree = example();
+
+    let mut paths: Vec<String> = Paths::new(&tree, PathOptions::default()).unwrap().map(|p| p.join(",")).collect();
+    paths.sort();
+
+    assert_eq!(paths, vec![
+        "start,A,b,A,c,A,end",
+        "start,A,b,A,end",
+        "start,A,b,end",
+        "start,A,c,A,b,A,end",
+        "start,A,c,A,b,end",
+        "start,A,c,A,end",
+        "start,A,end",
+        "start,b,A,c,A,end",
+        "start,b,A,end",
+        "start,b,end",
+    ]);
+
+    let options = PathOptions { revisits: 1, ..Default::default() };
+    assert_eq!(Paths::new(&tree, options).unwrap().count(), 36);
+}
+
+#[test]
+fn test_filters() {
+    let tree = example();
+
+    let count = |options: PathOptions| Paths::new(&tree, options).unwrap().count();
+
+    // Limits
+    assert_eq!(count(PathOptions { max_len: Some(4), ..Default::default() }), 4);
+    assert_eq!(count(PathOptions { max_count: Some(3), revisits: 1, ..Default::default() }), 3);
+
+    // Visit c and avoid b
+    let options = PathOptions { must_visit: vec!["c".to_string()], must_avoid: vec!["b".to_string()], ..Default::default() };
+    let paths: Vec<Vec<&str>> = Paths::new(&tree, options).unwrap().collect();
+    assert_eq!(paths, vec![vec!["start", "A", "c", "A", "end"]]);
+
+    // Output formats
+    let mut out = Vec::new();
+    let options = PathOptions { max_len: Some(3), ..Default::default() };
+
+    assert_eq!(write_paths(&mut out, Paths::new(&tree, options.clone()).unwrap(), PathFormat::Json).unwrap(), 2);
+
+    let mut lines: Vec<&str> = std::str::from_utf8(&out).unwrap().lines().collect();
+    lines.sort();
+    assert_eq!(lines, vec![r#"["start","A","end"]"#, r#"["start","b","end"]"#]);
+
+    let mut out = Vec::new();
+    write_paths(&mut out, Paths::new(&tree, options).unwrap(), PathFormat::Text).unwrap();
+    assert!(String::from_utf8(out).unwrap().contains("start,A,end\n"));
+
+    // Connected large caves need a length limit
+    let tree = super::load_buf("start-A\nA-B\nB-end".as_bytes()).unwrap();
+
+    assert!(Paths::new(&tree, PathOptions::default()).is_err());
+    assert_eq!(Paths::new(&tree, PathOptions { max_len: Some(6), ..Default::default() }).unwrap().count(), 2);
+}
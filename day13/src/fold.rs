@@ -0,0 +1,269 @@
+use std::collections::HashSet;
+use std::fmt::Write;
+
+use super::{Coord, Fold};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sheet {
+    pub dots: HashSet<Coord>,
+    pub width: u16,
+    pub height: u16,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SnapshotFormat {
+    Text,
+    Svg,
+}
+
+impl SnapshotFormat {
+
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "text" => Ok(SnapshotFormat::Text),
+            "svg" => Ok(SnapshotFormat::Svg),
+            _ => Err(format!("Unknown snapshot format '{}', expected text or svg", name)),
+        }
+    }
+
+    pub fn from_file_name(file: &str) -> Self {
+        match file.rsplit_once('.').map(|(_, ext)| ext) {
+            Some("svg") => SnapshotFormat::Svg,
+            _ => SnapshotFormat::Text,
+        }
+    }
+
+}
+
+// Size in pixels of a dot in SVG output
+const SVG_SCALE: usize = 10;
+
+impl Sheet {
+
+    pub fn new(dots: &[Coord]) -> Self {
+        // Sheet just covers the dots
+        let width = dots.iter().map(|d| d.x + 1).max().unwrap_or(0);
+        let height = dots.iter().map(|d| d.y + 1).max().unwrap_or(0);
+
+        Sheet {
+            dots: dots.iter().cloned().collect(),
+            width,
+            height,
+        }
+    }
+
+    pub fn fold(&self, fold: &Fold) -> Sheet {
+        match fold {
+            Fold::XAxis(pos) => {
+                let (dots, width) = Self::fold_axis(self.dots.iter().map(|d| (d.x, d.y)), *pos, self.width);
+
+                Sheet {
+                    dots: dots.map(|(x, y)| Coord::new(x, y)).collect(),
+                    width,
+                    height: self.height,
+                }
+            }
+            Fold::YAxis(pos) => {
+                let (dots, height) = Self::fold_axis(self.dots.iter().map(|d| (d.y, d.x)), *pos, self.height);
+
+                Sheet {
+                    dots: dots.map(|(y, x)| Coord::new(x, y)).collect(),
+                    width: self.width,
+                    height,
+                }
+            }
+            Fold::Diagonal => {
+                // Fold the part below the line x=y up over the part above
+                Sheet {
+                    dots: self.dots.iter().map(|d| if d.y > d.x { Coord::new(d.y, d.x) } else { d.clone() }).collect(),
+                    width: self.width.max(self.height),
+                    height: self.width.min(self.height),
+                }
+            }
+        }
+    }
+
+    fn fold_axis(dots: impl Iterator<Item = (u16, u16)>, pos: u16, size: u16) -> (impl Iterator<Item = (u16, u16)>, u16) {
+        // Fold the part past pos back over, shifting everything along if it overhangs the origin
+        let pos = pos as i32;
+        let far_edge = (size as i32 - 1).max(pos);
+        let overhang = (far_edge - 2 * pos).max(0);
+
+        let dots: Vec<(u16, u16)> = dots.map(|(a, b)| {
+            let a = a as i32;
+            let folded = if a > pos { 2 * pos - a } else { a };
+
+            ((folded + overhang) as u16, b)
+        }).collect();
+
+        // Dots on the fold line stay where they are
+        let size = dots.iter().map(|&(a, _)| a + 1).max().unwrap_or(0).max((pos + overhang) as u16);
+
+        (dots.into_iter(), size)
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                text.push(if self.dots.contains(&Coord::new(x, y)) { '#' } else { '.' });
+            }
+
+            text.push('\n');
+        }
+
+        text
+    }
+
+}
+
+pub fn fold_all(sheet: &Sheet, folds: &[Fold]) -> Vec<Sheet> {
+    // Sheet after each fold
+    let mut snapshots: Vec<Sheet> = Vec::with_capacity(folds.len());
+
+    for fold in folds {
+        let next = snapshots.last().unwrap_or(sheet).fold(fold);
+
+        snapshots.push(next);
+    }
+
+    snapshots
+}
+
+pub fn export_snapshots(sheet: &Sheet, folds: &[Fold], format: SnapshotFormat) -> String {
+    let snapshots = fold_all(sheet, folds);
+
+    let captioned: Vec<(String, &Sheet)> = std::iter::once(("Initial sheet".to_string(), sheet))
+        .chain(folds.iter().zip(&snapshots).enumerate().map(|(i, (fold, snapshot))| {
+            (format!("After fold {} ({})", i + 1, fold), snapshot)
+        }))
+        .collect();
+
+    match format {
+        SnapshotFormat::Text => captioned.iter()
+            .map(|(caption, sheet)| format!("{} {}x{}:\n{}", caption, sheet.width, sheet.height, sheet.to_text()))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        SnapshotFormat::Svg => to_svg(&captioned),
+    }
+}
+
+fn to_svg(captioned: &[(String, &Sheet)]) -> String {
+    // Stack the snapshots vertically, each with a caption and sheet outline
+    const CAPTION: usize = 24;
+    const MARGIN: usize = 10;
+
+    let width = captioned.iter().map(|(_, s)| s.width as usize * SVG_SCALE).max().unwrap_or(0).max(200) + 2 * MARGIN;
+    let height: usize = captioned.iter().map(|(_, s)| s.height as usize * SVG_SCALE + CAPTION + MARGIN).sum::<usize>() + MARGIN;
+
+    let mut svg = String::new();
+
+    writeln!(svg, "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\">", width, height).unwrap();
+    writeln!(svg, "<rect width=\"100%\" height=\"100%\" fill=\"white\"/>").unwrap();
+
+    let mut top = MARGIN;
+
+    for (caption, sheet) in captioned {
+        writeln!(svg, "<text x=\"{}\" y=\"{}\" font-family=\"monospace\" font-size=\"16\">{}</text>",
+            MARGIN, top + 16, caption).unwrap();
+
+        top += CAPTION;
+
+        writeln!(svg, "<g transform=\"translate({},{})\">", MARGIN, top).unwrap();
+        writeln!(svg, "<rect width=\"{}\" height=\"{}\" fill=\"#f4f0e0\" stroke=\"grey\"/>",
+            sheet.width as usize * SVG_SCALE, sheet.height as usize * SVG_SCALE).unwrap();
+
+        // Sort dots to keep the output stable
+        let mut dots: Vec<&Coord> = sheet.dots.iter().collect();
+        dots.sort_by_key(|d| (d.y, d.x));
+
+        for dot in dots {
+            writeln!(svg, "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"black\"/>",
+                dot.x as usize * SVG_SCALE, dot.y as usize * SVG_SCALE, SVG_SCALE, SVG_SCALE).unwrap();
+        }
+
+        writeln!(svg, "</g>").unwrap();
+
+        top += sheet.height as usize * SVG_SCALE + MARGIN;
+    }
+
+    writeln!(svg, "</svg>").unwrap();
+
+    svg
+}
+
+#[cfg(test)]
+fn sheet(rows: &[&str]) -> Sheet {
+    let dots: Vec<Coord> = rows.iter().enumerate().flat_map(|(y, row)| {
+        row.bytes().enumerate().filter(|&(_, b)| b == b'#').map(move |(x, _)| Coord::new(x as u16, y as u16))
+    }).collect();
+
+    Sheet { dots: dots.into_iter().collect(), width: rows[0].len() as u16, height: rows.len() as u16 }
+}
+
+#[test]
+fn test_example() {
+    let example = sheet(&[
+        "...#..#..#.",
+        "....#......",
+        "...........",
+        "#..........",
+        "...#....#.#",
+        "...........",
+        "...........",
+        "...........",
+        "...........",
+        "...........",
+        ".#....#.##.",
+        "....#......",
+        "......#...#",
+        "#..........",
+        "#.#........",
+    ]);
+
+    let snapshots = fold_all(&example, &[Fold::YAxis(7), Fold::XAxis(5)]);
+
+    assert_eq!(snapshots[0].dots.len(), 17);
+    assert_eq!(snapshots[1].to_text(), "\
+#####
+#...#
+#...#
+#...#
+#####
+.....
+.....
+");
+}
+
+#[test]
+fn test_overhang() {
+    // Folding near the origin flips the long part over and moves the origin
+    let folded = sheet(&["#..#..#"]).fold(&Fold::XAxis(1));
+
+    assert_eq!(folded.to_text(), "#..##\n");
+
+    // Same vertically
+    let folded = sheet(&["#", ".", ".", "#"]).fold(&Fold::YAxis(1));
+    assert_eq!(folded.to_text(), "#\n#\n");
+
+    // Fold at the origin
+    let folded = sheet(&["..#"]).fold(&Fold::XAxis(0));
+    assert_eq!(folded.to_text(), "#.\n");
+}
+
+#[test]
+fn test_diagonal() {
+    let folded = sheet(&["#..", "#..", ".#.", "#.."]).fold(&Fold::Diagonal);
+
+    assert_eq!((folded.width, folded.height), (4, 3));
+    assert_eq!(folded.to_text(), "##.#\n..#.\n....\n");
+
+    let text = export_snapshots(&sheet(&["#..", "..#"]), &[Fold::Diagonal, Fold::XAxis(1)], SnapshotFormat::Text);
+    assert_eq!(text, "Initial sheet 3x2:\n#..\n..#\n\nAfter fold 1 (fold along x=y) 3x2:\n#..\n..#\n\nAfter fold 2 (fold along x=1) 1x2:\n#\n#\n");
+
+    let svg = export_snapshots(&sheet(&["#."]), &[Fold::XAxis(1)], SnapshotFormat::Svg);
+    assert!(svg.starts_with("<svg"));
+    assert_eq!(svg.matches("fill=\"black\"").count(), 2);
+    assert!(svg.contains(">After fold 1 (fold along x=1)</text>"));
+}
@@ -1,3 +1,6 @@
+mod fold;
+mod ocr;
+
 use std::io::{BufRead, BufReader};
 use std::error::Error;
 use std::fs::File;
@@ -6,41 +9,72 @@ use std::cmp::max;
 use std::fmt;
 use memmap2::Mmap;
 
+use fold::{Sheet, SnapshotFormat};
+
 fn main() -> Result<(), Box<dyn Error>> {
+    // Process options
+    let mut input = "input13.txt".to_string();
+    let mut snapshots = None;
+    let mut snapshot_format = None;
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--input" => input = args.next().ok_or("--input expects a file name")?,
+            "--snapshots" => snapshots = Some(args.next().ok_or("--snapshots expects a file name")?),
+            "--snapshot-format" => {
+                snapshot_format = Some(SnapshotFormat::parse(&args.next().ok_or("--snapshot-format expects text or svg")?)?);
+            }
+            _ => return Err(format!("Unrecognised argument '{}'", arg).into()),
+        }
+    }
+
     // Load the input file
-    let (dots, folds) = load_input("input13.txt")?;
+    let (dots, folds) = load_input(&input)?;
+    let sheet = Sheet::new(&dots);
 
     // Run parts
-    part1(&dots, &folds);
-    part2(&dots, &folds);
+    part1(&sheet, &folds);
+    part2(&sheet, &folds);
+
+    if let Some(file) = snapshots {
+        // Write the sheet after each fold
+        let format = snapshot_format.unwrap_or_else(|| SnapshotFormat::from_file_name(&file));
+
+        std::fs::write(&file, fold::export_snapshots(&sheet, &folds, format))?;
+
+        println!("Fold snapshots written to {}", file);
+    }
 
     Ok(())
 }
 
-fn part1(dots: &[Coord], folds: &[Fold]) {
-    let mut work_dots: HashSet<Coord> = HashSet::from_iter(dots.iter().cloned());
-
-    work_dots = fold_page(work_dots, &folds[0]);
+fn part1(sheet: &Sheet, folds: &[Fold]) {
+    let folded = sheet.fold(&folds[0]);
 
-    println!("Part 1: Number of dots visible after first fold: {}", work_dots.len());
+    println!("Part 1: Number of dots visible after first fold: {}", folded.dots.len());
 }
 
-fn part2(dots: &[Coord], folds: &[Fold]) {
-    let mut work_dots: HashSet<Coord> = HashSet::from_iter(dots.iter().cloned());
+fn part2(sheet: &Sheet, folds: &[Fold]) {
+    let folded = fold::fold_all(sheet, folds).pop().unwrap_or_else(|| sheet.clone());
 
-    for fold in folds {
-        work_dots = fold_page(work_dots, fold);
+    match ocr::recognise(&folded.dots) {
+        Ok(code) => println!("Part 2: Code: {}", code),
+        Err(e) => {
+            println!("Part 2: Unable to read code ({}):", e);
+            print_dots(&folded.dots);
+        }
     }
+}
 
-    let max = work_dots.iter().fold((0, 0), |(maxx, maxy), dot| {
+fn print_dots(dots: &HashSet<Coord>) {
+    let max = dots.iter().fold((0, 0), |(maxx, maxy), dot| {
         (max(maxx, dot.x), max(maxy, dot.y))
     });
 
-    println!("Part 2:");
-
     for y in 0..=max.1 {
         for x in 0..=max.0 {
-            if work_dots.contains(&Coord {x, y}) {
+            if dots.contains(&Coord {x, y}) {
                 print!("\u{2588}")
             } else {
                 print!(" ")
@@ -50,19 +84,6 @@ fn part2(dots: &[Coord], folds: &[Fold]) {
     }
 }
 
-fn fold_page(dots: HashSet<Coord>, fold: &Fold) -> HashSet<Coord> {
-    let calc_fold = |pos: u16, coord: u16| if coord > pos { pos - (coord - pos) } else { coord };
-
-    let new_dots: HashSet<Coord> = dots.into_iter().map(|coord| {
-        match fold {
-            Fold::XAxis(pos) => Coord::new(calc_fold(*pos, coord.x), coord.y),
-            Fold::YAxis(pos) => Coord::new(coord.x, calc_fold(*pos, coord.y))
-        }
-    }).collect();
-
-    new_dots
-}
-
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 struct Coord {
     x: u16,
@@ -81,6 +102,8 @@ impl Coord {
 enum Fold {
     XAxis(u16),
     YAxis(u16),
+    // Along the line x=y
+    Diagonal,
 }
 
 impl Fold {
@@ -95,6 +118,16 @@ impl Fold {
 
 }
 
+impl fmt::Display for Fold {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Fold::XAxis(pos) => write!(f, "fold along x={}", pos),
+            Fold::YAxis(pos) => write!(f, "fold along y={}", pos),
+            Fold::Diagonal => write!(f, "fold along x=y"),
+        }
+    }
+}
+
 type ParseResult = (Vec<Coord>, Vec<Fold>);
 
 #[derive(Debug, PartialEq)]
@@ -175,6 +208,11 @@ fn load_buf(buf: &[u8]) -> Result<ParseResult, Box<dyn Error>> {
                 return Err(ParseError::Expect2FoldTerms.into());
             }
 
+            if let ["x", "y"] | ["y", "x"] = split[..] {
+                folds.push(Fold::Diagonal);
+                continue
+            }
+
             let axis = match split[0] {
                 "x" => Ok('x'),
                 "y" => Ok('y'),
@@ -239,6 +277,9 @@ mod tests {
         assert!(result.is_err());
         assert_eq!(result.unwrap_err().downcast_ref::<ParseError>().unwrap(), &ParseError::Expect2FoldTerms);
 
+        let result = gen_input("1,2", "fold along y=x");
+        assert_eq!(result.unwrap(), (vec![Coord::new(1, 2)], vec![Fold::Diagonal]));
+
         let result = gen_input("1,2", "fold along z=1");
         assert!(result.is_err());
         assert_eq!(result.unwrap_err().downcast_ref::<ParseError>().unwrap(), &ParseError::InvalidAxis("z".to_string()));
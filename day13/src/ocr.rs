@@ -0,0 +1,183 @@
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
+
+use super::Coord;
+
+const GLYPH_HEIGHT: usize = 6;
+
+// Block letters, 4x6 apart from the 5x6 Y. Blank columns are trimmed before matching
+const FONT: [(char, [&str; GLYPH_HEIGHT]); 18] = [
+    ('A', [".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', ["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', [".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+    ('E', ["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', ["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', [".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+    ('H', ["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('I', [".###", "..#.", "..#.", "..#.", "..#.", ".###"]),
+    ('J', ["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', ["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+    ('L', ["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('O', [".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', ["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('R', ["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', [".###", "#...", "#...", ".##.", "...#", "###."]),
+    ('U', ["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('Y', ["#...#", "#...#", ".#.#.", "..#..", "..#..", "..#.."]),
+    ('Z', ["####", "...#", "..#.", ".#..", "#...", "####"]),
+];
+
+#[derive(Debug, PartialEq)]
+pub enum OcrError {
+    NoDots,
+    TooTall(usize),
+    // Glyph number and column range of each glyph not in the font
+    Unrecognised(Vec<(usize, usize, usize)>),
+}
+
+impl fmt::Display for OcrError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OcrError::NoDots => write!(f, "no dots to recognise"),
+            OcrError::TooTall(height) => write!(f, "dots are {} rows high, expected {}", height, GLYPH_HEIGHT),
+            OcrError::Unrecognised(glyphs) => {
+                let positions: Vec<String> = glyphs.iter()
+                    .map(|(n, start, end)| format!("glyph {} (columns {}-{})", n + 1, start, end))
+                    .collect();
+
+                write!(f, "unrecognised {}", positions.join(", "))
+            }
+        }
+    }
+}
+
+impl Error for OcrError {
+}
+
+// Column bit masks of a glyph, top row in the lowest bit
+type Columns = Vec<u8>;
+
+fn font_columns(rows: &[&str; GLYPH_HEIGHT]) -> Columns {
+    let width = rows[0].len();
+
+    (0..width)
+        .map(|x| rows.iter().enumerate().fold(0, |mask, (y, row)| {
+            if row.as_bytes()[x] == b'#' { mask | (1 << y) } else { mask }
+        }))
+        .filter(|&mask| mask != 0)
+        .collect()
+}
+
+pub fn recognise(dots: &HashSet<Coord>) -> Result<String, OcrError> {
+    let min_x = dots.iter().map(|d| d.x).min().ok_or(OcrError::NoDots)?;
+    let min_y = dots.iter().map(|d| d.y).min().unwrap();
+    let max_x = dots.iter().map(|d| d.x).max().unwrap();
+    let max_y = dots.iter().map(|d| d.y).max().unwrap();
+
+    let height = (max_y - min_y) as usize + 1;
+
+    if height > GLYPH_HEIGHT {
+        return Err(OcrError::TooTall(height));
+    }
+
+    // Build column masks
+    let mut columns: Columns = vec![0; (max_x - min_x) as usize + 1];
+
+    for dot in dots {
+        columns[(dot.x - min_x) as usize] |= 1 << (dot.y - min_y);
+    }
+
+    let font: Vec<(char, Columns)> = FONT.iter().map(|(c, rows)| (*c, font_columns(rows))).collect();
+
+    // Split into glyphs on blank columns
+    let mut text = String::new();
+    let mut unrecognised = Vec::new();
+    let mut x = 0;
+
+    while x < columns.len() {
+        if columns[x] == 0 {
+            x += 1;
+            continue;
+        }
+
+        let start = x;
+
+        while x < columns.len() && columns[x] != 0 {
+            x += 1;
+        }
+
+        let glyph = &columns[start..x];
+
+        match font.iter().find(|(_, cols)| cols == glyph) {
+            Some((c, _)) => text.push(*c),
+            None => {
+                let first = min_x as usize + start;
+
+                unrecognised.push((text.chars().count() + unrecognised.len(), first, first + glyph.len() - 1));
+            }
+        }
+    }
+
+    if unrecognised.is_empty() {
+        Ok(text)
+    } else {
+        Err(OcrError::Unrecognised(unrecognised))
+    }
+}
+
+#[cfg(test)]
+fn render_text(text: &str) -> HashSet<Coord> {
+    // Draw text in the font with a blank column between glyphs
+    let mut dots = HashSet::new();
+    let mut left = 0;
+
+    for c in text.chars() {
+        let rows = &FONT.iter().find(|(f, _)| *f == c).unwrap().1;
+
+        for (y, row) in rows.iter().enumerate() {
+            for (x, b) in row.bytes().enumerate() {
+                if b == b'#' {
+                    dots.insert(Coord::new(left + x as u16, y as u16));
+                }
+            }
+        }
+
+        left += rows[0].len() as u16 + 1;
+    }
+
+    dots
+}
+
+#[test]
+fn test_recognise() {
+    let all: String = FONT.iter().map(|(c, _)| *c).collect();
+
+    assert_eq!(recognise(&render_text(&all)), Ok(all));
+    assert_eq!(recognise(&render_text("LRGPRECB")), Ok("LRGPRECB".to_string()));
+
+    // Offset from the origin
+    let shifted: HashSet<Coord> = render_text("HI").into_iter().map(|c| Coord::new(c.x + 7, c.y + 3)).collect();
+    assert_eq!(recognise(&shifted), Ok("HI".to_string()));
+}
+
+#[test]
+fn test_errors() {
+    assert_eq!(recognise(&HashSet::new()), Err(OcrError::NoDots));
+
+    // The example folds to a square
+    let mut dots: HashSet<Coord> = render_text("AB");
+
+    for i in 0..5 {
+        for (x, y) in [(i, 0), (i, 4), (0, i), (4, i)] {
+            dots.insert(Coord::new(20 + x, y));
+        }
+    }
+
+    let err = recognise(&dots).unwrap_err();
+    assert_eq!(err, OcrError::Unrecognised(vec![(2, 20, 24)]));
+    assert_eq!(err.to_string(), "unrecognised glyph 3 (columns 20-24)");
+
+    dots.insert(Coord::new(0, 6));
+    assert_eq!(recognise(&dots), Err(OcrError::TooTall(7)));
+}
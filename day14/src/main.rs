@@ -1,112 +1,218 @@
-use std::collections::{BTreeMap, HashMap};
-use std::io::{BufRead, BufReader};
+mod matrix;
+mod polymer;
+mod rules;
+mod series;
+
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, BufWriter};
 use std::error::Error;
 use std::fs::File;
 use std::fmt;
 use memmap2::Mmap;
 use itertools::Itertools;
-use num_format::{SystemLocale, ToFormattedString, Format as NumFormat};
+use num_bigint::BigUint;
+use num_format::{SystemLocale, ToFormattedString, Grouping, Format as NumFormat};
+
+use matrix::{Exact, Modular};
+use polymer::Polymer;
+use rules::{Condition, Rule, Rules};
+use series::{Series, SeriesFormat};
 
 type Elem = char;
 type Pair = (Elem, Elem);
-type Subs = HashMap<Pair, Elem>;
-type Count = u64;
-type PairCount = HashMap<Pair, Count>;
+type Count = BigUint;
 type FreqMap = BTreeMap<Elem, Count>;
 
+// Exact counts grow by about a bit a step, so larger step counts are done modulo a number
+const MAX_EXACT_STEPS: u64 = 10_000;
+// The series holds exact counts for every step
+const MAX_SERIES_STEPS: u64 = 1_000;
+const DEFAULT_MODULUS: u64 = 1_000_000_007;
+
 fn main() -> Result<(), Box<dyn Error>> {
     let locale = SystemLocale::default().unwrap();
 
+    // Process options
+    let mut input = "input14.txt".to_string();
+    let mut steps = None;
+    let mut series_file = None;
+    let mut series_format = None;
+    let mut show_extremes = false;
+    let mut modulus = None;
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--input" => input = args.next().ok_or("--input expects a file name")?,
+            "--steps" => steps = Some(args.next().ok_or("--steps expects a step count")?.parse::<u64>()?),
+            "--series" => series_file = Some(args.next().ok_or("--series expects a file name")?),
+            "--extremes" => show_extremes = true,
+            "--modulus" => modulus = Some(args.next().ok_or("--modulus expects a number")?.parse::<u64>()?),
+            "--series-format" => {
+                series_format = Some(SeriesFormat::parse(&args.next().ok_or("--series-format expects csv or json")?)?);
+            }
+            _ => return Err(format!("Unrecognised argument '{}'", arg).into()),
+        }
+    }
+
+    let show_series = series_file.is_some() || show_extremes;
+
+    match steps {
+        None if show_series => return Err("--series and --extremes need a step count from --steps".into()),
+        Some(steps) if show_series && steps > MAX_SERIES_STEPS => {
+            return Err(format!("--series and --extremes are limited to {} steps", MAX_SERIES_STEPS).into());
+        }
+        _ => (),
+    }
+
+    let modular = Modular::new(modulus.unwrap_or(DEFAULT_MODULUS))?;
+
     // Load the input file
-    let (polymer, subs) = load_input("input14.txt")?;
+    let (polymer, rules) = load_input(&input)?;
+    let polymer = Polymer::new(&polymer, &rules)?;
 
     // Run parts
-    part1(&polymer, &subs, &locale);
-    part2(&polymer, &subs, &locale);
+    part1(&polymer, &locale);
+    part2(&polymer, &locale);
+    huge(&polymer, &modular, &locale);
+
+    if let Some(steps) = steps {
+        if modulus.is_some() || steps > MAX_EXACT_STEPS {
+            report_modular(&polymer, steps, &modular, &locale);
+        } else {
+            report("Steps", &polymer, steps, &locale);
+        }
+    }
+
+    if let (Some(steps), true) = (steps, show_series) {
+        // Element and pair counts for each step
+        let series = polymer.series(&Exact, steps);
+
+        if show_extremes {
+            extremes(&series, steps);
+        }
+
+        if let Some(file) = series_file {
+            let format = series_format.unwrap_or_else(|| SeriesFormat::from_file_name(&file));
+            let mut out = BufWriter::new(File::create(&file)?);
+
+            series.write(&mut out, format)?;
+
+            println!("Frequency time series written to {}", file);
+        }
+    }
 
     Ok(())
 }
 
-fn part1<F: NumFormat>(polymer: &str, subs: &Subs, locale: &F) {
-    const ITERS: usize = 10;
+fn part1<F: NumFormat>(polymer: &Polymer, locale: &F) {
+    report("Part 1", polymer, 10, locale);
+}
+
+fn part2<F: NumFormat>(polymer: &Polymer, locale: &F) {
+    report("Part 2", polymer, 40, locale);
+}
 
-    let freq_map = run_sub(polymer, subs, ITERS);
+fn report<F: NumFormat>(title: &str, polymer: &Polymer, iters: u64, locale: &F) {
+    let freq_map = run_sub(polymer, iters);
 
     let (min_cnt, max_cnt) = freq_min_max(&freq_map);
 
-    println!("Part 1: frequencies after {} iterations:", ITERS);
+    println!("{}: frequencies after {} iterations:", title, iters);
     dump_freq_map(12, &freq_map, locale);
  
-    println!("        max {}; min {}", max_cnt.to_formatted_string(locale), min_cnt.to_formatted_string(locale));
+    println!("        max {}; min {}", max_cnt.format_count(locale), min_cnt.format_count(locale));
     println!("        difference (answer): {}", max_cnt - min_cnt);
 }
 
-fn part2<F: NumFormat>(polymer: &str, subs: &Subs, locale: &F) {
-    const ITERS: usize = 40;
+fn extremes(series: &Series<Count>, iters: u64) {
+    let step_str = |step: Option<usize>| step.map_or("never".to_string(), |s| s.to_string());
 
-    let freq_map = run_sub(polymer, subs, 40);
+    println!("First step each element is most / least common in {} iterations:", iters);
 
-    let (min_cnt, max_cnt) = freq_min_max(&freq_map);
+    for (elem, extremes) in series.first_extremes() {
+        println!("            {} = {:>5} / {:>5}", elem, step_str(extremes.most), step_str(extremes.least));
+    }
+}
+
+fn huge<F: NumFormat>(polymer: &Polymer, modular: &Modular, locale: &F) {
+    report_modular(polymer, 1_000_000_000_000_000, modular, locale);
+}
+
+fn report_modular<F: NumFormat>(polymer: &Polymer, iters: u64, modular: &Modular, locale: &F) {
+    let freq_map = polymer.element_counts(modular, iters);
 
-    println!("Part 2: frequencies after {} iterations:", ITERS);
+    println!("Frequencies after {} iterations mod {}:", iters.format_count(locale), modular.modulus().format_count(locale));
     dump_freq_map(12, &freq_map, locale);
- 
-    println!("        max {}; min {}", max_cnt.to_formatted_string(locale), min_cnt.to_formatted_string(locale));
-    println!("        difference (answer): {}", max_cnt - min_cnt);
 }
 
-fn run_sub(polymer: &str, subs: &Subs, iters: usize) -> FreqMap {
-    let mut counts = PairCount::new();
+fn run_sub(polymer: &Polymer, iters: u64) -> FreqMap {
+    // Exact element frequencies after a number of steps
+    polymer.element_counts(&Exact, iters)
+}
 
-    // Build initial counts hash map
-    for (c1, c2) in polymer.chars().tuple_windows() {
-        *counts.entry((c1, c2)).or_default() += 1;
-    }
+fn freq_min_max<T: Ord + Clone>(freq_map: &BTreeMap<Elem, T>) -> (T, T) {
+    // Get min and max frequency entries
+    let max_cnt = freq_map.values().max().unwrap().clone();
+    let min_cnt = freq_map.values().min().unwrap().clone();
 
-    for _ in 0..iters {
-        let mut new_counts = PairCount::with_capacity(subs.len());
+    (min_cnt, max_cnt)
+}
 
-        // Build new counts hashmap
-        for (pair @ &(c1, c2), &count) in &counts {
-            let &sub = subs.get(pair).unwrap();
+trait FormatCount {
+    fn format_count<F: NumFormat>(&self, locale: &F) -> String;
+}
 
-            *new_counts.entry((c1, sub)).or_default() += count;
-            *new_counts.entry((sub, c2)).or_default() += count;
-        }
+impl FormatCount for u64 {
+    fn format_count<F: NumFormat>(&self, locale: &F) -> String {
+        self.to_formatted_string(locale)
+    }
+}
 
-        counts = new_counts;
+impl FormatCount for BigUint {
+    fn format_count<F: NumFormat>(&self, locale: &F) -> String {
+        // num_format only handles primitive integers, so group larger counts by hand
+        match u64::try_from(self) {
+            Ok(count) => count.format_count(locale),
+            Err(_) => group_digits(&self.to_string(), locale),
+        }
     }
+}
 
-    // Build character frequency map
-    let mut freq_map = FreqMap::new();
-    
-    *freq_map.entry(polymer.chars().next().unwrap()).or_default() += 1;
+fn group_digits<F: NumFormat>(digits: &str, locale: &F) -> String {
+    // Group digits from the right in the same way as the locale
+    let separator = locale.separator().into_str();
+    let mut formatted = String::new();
 
-    for ((_, c2), count) in counts {
-        *freq_map.entry(c2).or_default() += count;
-    }
+    for (i, digit) in digits.chars().enumerate() {
+        let from_right = digits.len() - i;
 
-    freq_map
-}
+        let separate = i > 0 && match locale.grouping() {
+            Grouping::Standard => from_right.is_multiple_of(3),
+            Grouping::Indian => from_right == 3 || (from_right > 3 && (from_right - 3).is_multiple_of(2)),
+            Grouping::Posix => false,
+        };
 
-fn freq_min_max(freq_map: &FreqMap) -> (Count, Count) {
-    // Get min and max frequency entries
-    let max_cnt = freq_map.iter().map(|(_, &cnt)| cnt).max().unwrap();
-    let min_cnt = freq_map.iter().map(|(_, &cnt)| cnt).min().unwrap();
+        if separate {
+            formatted += separator;
+        }
 
-    (min_cnt, max_cnt)
+        formatted.push(digit);
+    }
+
+    formatted
 }
 
-fn dump_freq_map<F: NumFormat>(indent: usize, freq_map: &FreqMap, locale: &F) {
+fn dump_freq_map<T: Ord + FormatCount, F: NumFormat>(indent: usize, freq_map: &BTreeMap<Elem, T>, locale: &F) {
     // Build first column output vector
-    let output1: Vec<_> = freq_map.iter().map(|(&c, &cnt)| (c, cnt, cnt.to_formatted_string(locale))).collect();
+    let output1: Vec<_> = freq_map.iter().map(|(&c, cnt)| (c, cnt, cnt.format_count(locale))).collect();
 
     // Build second column output vector
     let mut output2: Vec<_> = output1.iter().collect();
     output2.sort_by_key(|&(_, cnt, _)| cnt);
 
     // Work out max length of formatted number
-    let max_len = output1.iter().map(|(.., cntstr)| cntstr.len()).max().unwrap();
+    let max_len = output1.iter().map(|(.., cntstr)| cntstr.chars().count()).max().unwrap();
 
     // Output the tables
     for ((c1, _, cntstr1), (c2, _, cntstr2)) in output1.iter().zip(output2.iter()) {
@@ -115,21 +221,23 @@ fn dump_freq_map<F: NumFormat>(indent: usize, freq_map: &FreqMap, locale: &F) {
     }
 }
 
-type ParseResult = (String, Subs);
+type ParseResult = (String, Rules);
 
 #[derive(Debug, PartialEq)]
 enum ParseError {
-    ExpectArrow,
-    Expect2SubSrcChars,
-    Expect1SubDstChar,
+    ExpectArrow(usize),
+    Expect2SubSrcChars(usize),
+    InvalidSubDst(usize),
+    InvalidCondition(usize, String),
 }
 
 impl fmt::Display for ParseError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            ParseError::ExpectArrow => write!(f, "single arrow operator expected"),
-            ParseError::Expect2SubSrcChars => write!(f, "two characters expected in substitution source"),
-            ParseError::Expect1SubDstChar => write!(f, "one characters expected in substitution destination"),
+            ParseError::ExpectArrow(line) => write!(f, "line {}: single arrow operator expected", line),
+            ParseError::Expect2SubSrcChars(line) => write!(f, "line {}: two characters expected in substitution source", line),
+            ParseError::InvalidSubDst(line) => write!(f, "line {}: substitution destination can't contain spaces", line),
+            ParseError::InvalidCondition(line, msg) => write!(f, "line {}: {}", line, msg),
         }
     }
 }
@@ -157,12 +265,13 @@ fn load_buf(buf: &[u8]) -> Result<ParseResult, Box<dyn Error>> {
 
     // Create returns
     let mut polymer = String::new();
-    let mut subs = HashMap::new();
+    let mut rules = Rules::default();
     let mut in_subs = false;
 
     // Iterate lines
-    for line_res in buf_reader.lines() {
+    for (line_no, line_res) in buf_reader.lines().enumerate() {
         let line = line_res?;
+        let line_no = line_no + 1;
 
         if line.is_empty() {
             if !in_subs { in_subs = true; }
@@ -170,37 +279,49 @@ fn load_buf(buf: &[u8]) -> Result<ParseResult, Box<dyn Error>> {
         }
 
         if in_subs {
-            let parts: Vec<&str> = line.split(" -> ").collect();
+            // AB -> C, AB -> CDE or AB -> with no insertion, optionally followed by @ and steps to fire on
+            let parts: Vec<&str> = line.split("->").collect();
 
             if parts.len() != 2 {
-                return Err(ParseError::ExpectArrow.into());
+                return Err(ParseError::ExpectArrow(line_no).into());
             }
 
-            if parts[0].len() != 2 {
-                return Err(ParseError::Expect2SubSrcChars.into());
-            }
+            let src = parts[0].trim();
 
-            if parts[1].len() != 1 {
-                return Err(ParseError::Expect1SubDstChar.into());
+            if src.chars().count() != 2 {
+                return Err(ParseError::Expect2SubSrcChars(line_no).into());
             }
 
-            let src = parts[0].chars().next_tuple().unwrap();
-            let dst = parts[1].chars().next().unwrap();
+            let (dst, when) = match parts[1].split_once('@') {
+                Some((dst, when)) => {
+                    let when = when.split(',')
+                        .map(Condition::parse)
+                        .collect::<Result<Vec<Condition>, String>>()
+                        .map_err(|msg| ParseError::InvalidCondition(line_no, msg))?;
+
+                    (dst.trim(), when)
+                }
+                None => (parts[1].trim(), Vec::new()),
+            };
+
+            if dst.contains(char::is_whitespace) {
+                return Err(ParseError::InvalidSubDst(line_no).into());
+            }
 
-            subs.insert(src, dst);
+            rules.add(src.chars().next_tuple().unwrap(), Rule { insert: dst.chars().collect(), when });
         } else {
             polymer += &line;
         }
     }
    
-    Ok((polymer, subs))
+    Ok((polymer, rules))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    const TEST_INPUT: &str = "\
+    pub const TEST_INPUT: &str = "\
 NNCB
 
 CH -> B
@@ -222,19 +343,60 @@ CN -> C";
 
     #[test]
     fn test_count() {
-        let (polymer, subs) = load_buf(TEST_INPUT.as_bytes()).unwrap();
+        let (polymer, rules) = load_buf(TEST_INPUT.as_bytes()).unwrap();
+        let polymer = Polymer::new(&polymer, &rules).unwrap();
 
-        let freqs = run_sub(&polymer, &subs, 10);
+        let freqs = run_sub(&polymer, 10);
         let (min, max) = freq_min_max(&freqs);
 
-        assert_eq!(min, 161);
-        assert_eq!(max, 1749);
+        assert_eq!(min, BigUint::from(161u64));
+        assert_eq!(max, BigUint::from(1749u64));
 
-        let freqs = run_sub(&polymer, &subs, 40);
+        let freqs = run_sub(&polymer, 40);
         let (min, max) = freq_min_max(&freqs);
 
-        assert_eq!(min, 3849876073);
-        assert_eq!(max, 2192039569602);
+        assert_eq!(min, BigUint::from(3849876073u64));
+        assert_eq!(max, BigUint::from(2192039569602u64));
+    }
+
+    #[test]
+    fn test_format_count() {
+        use num_format::{CustomFormat, Locale};
+
+        let huge = BigUint::from(u64::MAX) * 1000u64 + 123u64;
+
+        assert_eq!(12345678u64.format_count(&Locale::en), "12,345,678");
+        assert_eq!(BigUint::from(12345678u64).format_count(&Locale::en), "12,345,678");
+        assert_eq!(huge.format_count(&Locale::en), "18,446,744,073,709,551,615,123");
+
+        let indian = CustomFormat::builder().grouping(Grouping::Indian).separator(",").build().unwrap();
+
+        assert_eq!(BigUint::from(12345678u64).format_count(&indian), "1,23,45,678");
+        assert_eq!(huge.format_count(&indian), "18,44,67,44,07,37,09,55,16,15,123");
+
+        let posix = CustomFormat::builder().grouping(Grouping::Posix).build().unwrap();
+
+        assert_eq!(huge.format_count(&posix), "18446744073709551615123");
+    }
+
+    #[test]
+    fn test_parser() {
+        let (polymer, rules) = load_buf("AB\n\nAB -> XYZ @ 1, 4-6, %7=2\nBA ->\nAA->B".as_bytes()).unwrap();
+
+        assert_eq!(polymer, "AB");
+        assert_eq!(rules.get(&('A', 'B')), &[Rule {
+            insert: vec!['X', 'Y', 'Z'],
+            when: vec![Condition::At(1), Condition::Range(4, 6), Condition::Every { period: 7, offset: 2 }],
+        }]);
+        assert_eq!(rules.get(&('B', 'A')), &[Rule { insert: vec![], when: vec![] }]);
+        assert_eq!(rules.get(&('A', 'A')), &[Rule { insert: vec!['B'], when: vec![] }]);
+
+        let error = |rule: &str| load_buf(format!("AB\n\nAB -> C\n{}", rule).as_bytes()).unwrap_err().to_string();
+
+        assert_eq!(error("AB C"), "line 4: single arrow operator expected");
+        assert_eq!(error("ABC -> D"), "line 4: two characters expected in substitution source");
+        assert_eq!(error("AB -> C D"), "line 4: substitution destination can't contain spaces");
+        assert_eq!(error("AB -> C @ 3-x"), "line 4: invalid step number 'x'");
     }
 
 }
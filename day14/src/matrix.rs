@@ -0,0 +1,249 @@
+use num_bigint::BigUint;
+
+pub trait Arithmetic {
+    type Value: Clone + PartialEq;
+
+    fn zero(&self) -> Self::Value;
+    fn value(&self, value: u64) -> Self::Value;
+    fn add(&self, a: &Self::Value, b: &Self::Value) -> Self::Value;
+    fn mul(&self, a: &Self::Value, b: &Self::Value) -> Self::Value;
+
+    fn sum_products<'a>(&self, terms: impl Iterator<Item = (&'a Self::Value, &'a Self::Value)>) -> Self::Value
+        where Self::Value: 'a {
+        terms.fold(self.zero(), |sum, (a, b)| self.add(&sum, &self.mul(a, b)))
+    }
+}
+
+// Exact arithmetic using arbitrary precision integers
+pub struct Exact;
+
+impl Arithmetic for Exact {
+    type Value = BigUint;
+
+    fn zero(&self) -> BigUint {
+        BigUint::default()
+    }
+
+    fn value(&self, value: u64) -> BigUint {
+        BigUint::from(value)
+    }
+
+    fn add(&self, a: &BigUint, b: &BigUint) -> BigUint {
+        a + b
+    }
+
+    fn mul(&self, a: &BigUint, b: &BigUint) -> BigUint {
+        a * b
+    }
+}
+
+// Arithmetic modulo a given value
+pub struct Modular {
+    modulus: u64,
+}
+
+impl Modular {
+
+    pub fn new(modulus: u64) -> Result<Self, String> {
+        if modulus == 0 {
+            return Err("Modulus must be at least 1".to_string());
+        }
+
+        Ok(Modular { modulus })
+    }
+
+    pub fn modulus(&self) -> u64 {
+        self.modulus
+    }
+
+}
+
+impl Arithmetic for Modular {
+    type Value = u64;
+
+    fn zero(&self) -> u64 {
+        0
+    }
+
+    fn value(&self, value: u64) -> u64 {
+        value % self.modulus
+    }
+
+    fn add(&self, a: &u64, b: &u64) -> u64 {
+        ((*a as u128 + *b as u128) % self.modulus as u128) as u64
+    }
+
+    fn mul(&self, a: &u64, b: &u64) -> u64 {
+        ((*a as u128 * *b as u128) % self.modulus as u128) as u64
+    }
+
+    fn sum_products<'a>(&self, terms: impl Iterator<Item = (&'a u64, &'a u64)>) -> u64 {
+        // Only reduce when the running total would overflow
+        let modulus = self.modulus as u128;
+
+        let sum = terms.fold(0u128, |sum, (&a, &b)| {
+            let product = a as u128 * b as u128;
+
+            sum.checked_add(product).unwrap_or((sum % modulus) + product)
+        });
+
+        (sum % modulus) as u64
+    }
+}
+
+// Whether a count is non-zero, follows exactly which pairs can appear
+pub struct Presence;
+
+impl Arithmetic for Presence {
+    type Value = bool;
+
+    fn zero(&self) -> bool {
+        false
+    }
+
+    fn value(&self, value: u64) -> bool {
+        value > 0
+    }
+
+    fn add(&self, a: &bool, b: &bool) -> bool {
+        *a || *b
+    }
+
+    fn mul(&self, a: &bool, b: &bool) -> bool {
+        *a && *b
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Matrix<T> {
+    size: usize,
+    elems: Vec<T>,
+}
+
+impl<T: Clone> Matrix<T> {
+
+    fn new<A: Arithmetic<Value = T>>(arith: &A, size: usize) -> Self {
+        Matrix { size, elems: vec![arith.zero(); size * size] }
+    }
+
+    pub fn identity<A: Arithmetic<Value = T>>(arith: &A, size: usize) -> Self {
+        let mut matrix = Self::new(arith, size);
+
+        for i in 0..size {
+            matrix.set(i, i, arith.value(1));
+        }
+
+        matrix
+    }
+
+    pub fn from_u64<A: Arithmetic<Value = T>>(arith: &A, size: usize, elems: &[u64]) -> Self {
+        assert_eq!(elems.len(), size * size, "Matrix element count incorrect");
+
+        Matrix { size, elems: elems.iter().map(|&e| arith.value(e)).collect() }
+    }
+
+    fn get(&self, row: usize, col: usize) -> &T {
+        &self.elems[(row * self.size) + col]
+    }
+
+    fn set(&mut self, row: usize, col: usize, value: T) {
+        self.elems[(row * self.size) + col] = value;
+    }
+
+    pub fn mul<A: Arithmetic<Value = T>>(&self, arith: &A, other: &Self) -> Self {
+        let mut result = Self::new(arith, self.size);
+
+        // Walk the columns of the other matrix as rows
+        let other = other.transpose();
+
+        for row in 0..self.size {
+            let lhs = &self.elems[row * self.size..(row + 1) * self.size];
+
+            for col in 0..self.size {
+                let rhs = &other.elems[col * self.size..(col + 1) * self.size];
+
+                result.set(row, col, arith.sum_products(lhs.iter().zip(rhs)));
+            }
+        }
+
+        result
+    }
+
+    fn transpose(&self) -> Self {
+        let elems = (0..self.size * self.size).map(|i| self.get(i % self.size, i / self.size).clone()).collect();
+
+        Matrix { size: self.size, elems }
+    }
+
+    pub fn pow<A: Arithmetic<Value = T>>(&self, arith: &A, mut exp: u64) -> Self {
+        // Raise to a power by repeated squaring
+        let mut result = Self::identity(arith, self.size);
+        let mut square = self.clone();
+
+        while exp > 0 {
+            if exp & 1 == 1 {
+                result = result.mul(arith, &square);
+            }
+
+            exp >>= 1;
+
+            if exp > 0 {
+                square = square.mul(arith, &square);
+            }
+        }
+
+        result
+    }
+
+    pub fn mul_vec<A: Arithmetic<Value = T>>(&self, arith: &A, vec: &[T]) -> Vec<T> {
+        (0..self.size).map(|row| {
+            arith.sum_products((0..self.size).map(|col| (self.get(row, col), &vec[col])))
+        }).collect()
+    }
+
+}
+
+#[test]
+fn test_sum_products() {
+    // Modular sums of products near u64::MAX agree with the exact values
+    let arith = Modular::new(u64::MAX - 58).unwrap();
+    let values: Vec<u64> = (0..50).map(|i| u64::MAX - i * 1_000_003).collect();
+
+    let exact = values.iter().zip(values.iter().rev()).fold(BigUint::default(), |sum, (&a, &b)| sum + BigUint::from(a) * b);
+    let modular = arith.sum_products(values.iter().zip(values.iter().rev()));
+
+    assert_eq!(BigUint::from(modular), exact % (u64::MAX - 58));
+}
+
+#[test]
+fn test_transitions() {
+    // Pair transitions as (to, from) with a pair which is never produced (row 0) and one which
+    // produces nothing (column 2)
+    let elems = [0, 0, 0, 2, 2, 0, 1, 3, 0];
+    let initial = [1u64, 2, 3];
+
+    // Repeated squaring agrees with stepping
+    let matrix = Matrix::from_u64(&Exact, 3, &elems);
+    let exact = matrix.pow(&Exact, 80).mul_vec(&Exact, &initial.map(BigUint::from));
+    let stepped = (0..80).fold(initial.map(BigUint::from).to_vec(), |counts, _| matrix.mul_vec(&Exact, &counts));
+
+    assert_eq!(exact, stepped);
+    assert_eq!(exact[0], BigUint::default());
+    assert!(exact[1].bits() > 64);
+
+    // Modular counts match the exact counts
+    let arith = Modular::new(1_000_003).unwrap();
+    let modular = Matrix::from_u64(&arith, 3, &elems).pow(&arith, 80).mul_vec(&arith, &initial);
+
+    for (exact, modular) in exact.iter().zip(&modular) {
+        assert_eq!(exact % 1_000_003u64, BigUint::from(*modular));
+    }
+
+    // Presence follows which counts are non-zero
+    let present = Matrix::from_u64(&Presence, 3, &elems).pow(&Presence, 80).mul_vec(&Presence, &initial.map(|c| c > 0));
+
+    assert_eq!(present, exact.iter().map(|count| *count != BigUint::default()).collect::<Vec<_>>());
+    assert_eq!(Matrix::from_u64(&Presence, 3, &elems).pow(&Presence, 2).mul_vec(&Presence, &[false, false, true]), vec![false; 3]);
+
+    assert!(Modular::new(0).is_err());
+}
@@ -0,0 +1,334 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::ops::RangeInclusive;
+
+use itertools::Itertools;
+
+use super::{Elem, Pair};
+use super::matrix::{Arithmetic, Matrix, Presence};
+use super::rules::Rules;
+use super::series::Series;
+
+pub struct Polymer {
+    first: Elem,
+    rules: Rules,
+    // Every pair which can appear in the polymer
+    pairs: Vec<Pair>,
+    initial: Vec<u64>,
+    // Pairs produced by each of the rules for each pair
+    outputs: Vec<Vec<Vec<usize>>>,
+}
+
+impl Polymer {
+
+    pub fn new(polymer: &str, rules: &Rules) -> Result<Self, String> {
+        let first = polymer.chars().next().ok_or("Empty polymer")?;
+
+        let mut pairs: Vec<Pair> = Vec::new();
+        let mut ids: HashMap<Pair, usize> = HashMap::new();
+
+        let mut intern = |pair: Pair, pairs: &mut Vec<Pair>| *ids.entry(pair).or_insert_with(|| {
+            pairs.push(pair);
+            pairs.len() - 1
+        });
+
+        // Count the pairs in the template
+        let mut initial = Vec::new();
+
+        for pair in polymer.chars().tuple_windows() {
+            let id = intern(pair, &mut pairs);
+
+            initial.resize(pairs.len(), 0);
+            initial[id] += 1;
+        }
+
+        // Follow the rules to find every reachable pair
+        let mut outputs = Vec::new();
+        let mut next = 0;
+
+        while next < pairs.len() {
+            let pair @ (c1, c2) = pairs[next];
+
+            outputs.push(rules.get(&pair).iter().map(|rule| {
+                // Pairs in the inserted chain
+                std::iter::once(c1).chain(rule.insert.iter().copied()).chain([c2]).tuple_windows()
+                    .map(|pair| intern(pair, &mut pairs))
+                    .collect()
+            }).collect());
+
+            next += 1;
+        }
+
+        initial.resize(pairs.len(), 0);
+
+        Ok(Polymer {
+            first,
+            rules: rules.clone(),
+            pairs,
+            initial,
+            outputs,
+        })
+    }
+
+    pub fn element_counts<A: Arithmetic>(&self, arith: &A, steps: u64) -> BTreeMap<Elem, A::Value> {
+        let counts = self.pair_counts(arith, steps);
+
+        // Counts can wrap round to zero, so find the pairs which appear separately
+        let present = self.pair_counts(&Presence, steps);
+
+        // Each element is the second of a pair apart from the first in the polymer
+        let mut elements = BTreeMap::from([(self.first, arith.value(1))]);
+
+        for ((&(_, c2), count), present) in self.pairs.iter().zip(&counts).zip(present) {
+            // Skip pairs which haven't appeared yet
+            if !present {
+                continue
+            }
+
+            let total = elements.entry(c2).or_insert_with(|| arith.zero());
+
+            *total = arith.add(total, count);
+        }
+
+        elements
+    }
+
+    pub fn series<A: Arithmetic>(&self, arith: &A, steps: u64) -> Series<A::Value> {
+        // Pairs in sorted order
+        let mut order: Vec<usize> = (0..self.pairs.len()).collect();
+        order.sort_by_key(|&i| self.pairs[i]);
+
+        let elements: Vec<Elem> = self.pairs.iter().map(|&(_, c2)| c2).chain([self.first]).collect::<BTreeSet<_>>()
+            .into_iter().collect();
+
+        let mut series = Series {
+            pairs: order.iter().map(|&i| self.pairs[i]).collect(),
+            elements,
+            element_counts: Vec::new(),
+            pair_counts: Vec::new(),
+        };
+
+        let mut counts: Vec<A::Value> = self.initial.iter().map(|&c| arith.value(c)).collect();
+
+        for step in 0..=steps {
+            if step > 0 {
+                counts = self.step(arith, &counts, step);
+            }
+
+            // Each element is the second of a pair apart from the first in the polymer
+            let element_counts = series.elements.iter().map(|&e| {
+                let first = arith.value(u64::from(e == self.first));
+
+                self.pairs.iter().zip(&counts).filter(|(&(_, c2), _)| c2 == e).fold(first, |sum, (_, count)| arith.add(&sum, count))
+            }).collect();
+
+            series.element_counts.push(element_counts);
+            series.pair_counts.push(order.iter().map(|&i| counts[i].clone()).collect());
+        }
+
+        series
+    }
+
+    fn pair_counts<A: Arithmetic>(&self, arith: &A, steps: u64) -> Vec<A::Value> {
+        let initial: Vec<A::Value> = self.initial.iter().map(|&c| arith.value(c)).collect();
+
+        // Compare the cost of stepping with the cost of repeated squaring
+        let (fixed, period) = self.rules.schedule();
+
+        // Rules which never repeat within a u64 of steps can only be stepped
+        let Some(period) = period else {
+            return self.stepped(arith, initial, 1..=steps);
+        };
+
+        let size = self.pairs.len() as u128;
+        let step_cost = steps as u128 * self.transitions(1).len() as u128;
+        let pow_cost = size * size * size * (period as u128 + (u64::BITS - steps.leading_zeros()) as u128);
+
+        if step_cost <= pow_cost || steps <= fixed {
+            self.stepped(arith, initial, 1..=steps)
+        } else {
+            self.periodic(arith, initial, steps)
+        }
+    }
+
+    fn stepped<A: Arithmetic>(&self, arith: &A, counts: Vec<A::Value>, steps: RangeInclusive<u64>) -> Vec<A::Value> {
+        steps.fold(counts, |counts, step| self.step(arith, &counts, step))
+    }
+
+    fn periodic<A: Arithmetic>(&self, arith: &A, counts: Vec<A::Value>, steps: u64) -> Vec<A::Value> {
+        let (fixed, period) = self.rules.schedule();
+
+        let Some(period) = period else {
+            return self.stepped(arith, counts, 1..=steps);
+        };
+
+        // Step through the steps with one-off rules
+        let counts = self.stepped(arith, counts, 1..=fixed.min(steps));
+
+        if steps <= fixed {
+            return counts;
+        }
+
+        // After that the rules repeat every period
+        let (cycles, rest) = ((steps - fixed) / period, (steps - fixed) % period);
+
+        let cycle = (fixed + 1..=fixed + period).fold(Matrix::identity(arith, self.pairs.len()), |matrix, step| {
+            self.transition_matrix(arith, step).mul(arith, &matrix)
+        });
+
+        let counts = cycle.pow(arith, cycles).mul_vec(arith, &counts);
+
+        self.stepped(arith, counts, steps - rest + 1..=steps)
+    }
+
+    fn transitions(&self, step: u64) -> Vec<(usize, usize)> {
+        // Pairs produced by each pair on a step as (to pair, from pair)
+        let mut transitions = Vec::new();
+
+        for (from, &pair) in self.pairs.iter().enumerate() {
+            match self.rules.get(&pair).iter().position(|rule| rule.fires(step)) {
+                Some(rule) => transitions.extend(self.outputs[from][rule].iter().map(|&to| (to, from))),
+                // Left untouched
+                None => transitions.push((from, from)),
+            }
+        }
+
+        transitions
+    }
+
+    fn step<A: Arithmetic>(&self, arith: &A, counts: &[A::Value], step: u64) -> Vec<A::Value> {
+        let mut next = vec![arith.zero(); counts.len()];
+
+        for (to, from) in self.transitions(step) {
+            next[to] = arith.add(&next[to], &counts[from]);
+        }
+
+        next
+    }
+
+    fn transition_matrix<A: Arithmetic>(&self, arith: &A, step: u64) -> Matrix<A::Value> {
+        let size = self.pairs.len();
+        let mut elems = vec![0; size * size];
+
+        for (to, from) in self.transitions(step) {
+            elems[(to * size) + from] += 1;
+        }
+
+        Matrix::from_u64(arith, size, &elems)
+    }
+
+}
+
+#[test]
+fn test_element_counts() {
+    use num_bigint::BigUint;
+
+    use super::matrix::{Exact, Modular};
+    use super::{load_buf, tests::TEST_INPUT};
+
+    let (template, subs) = load_buf(TEST_INPUT.as_bytes()).unwrap();
+    let polymer = Polymer::new(&template, &subs).unwrap();
+
+    let counts = |steps| -> Vec<(Elem, u64)> {
+        polymer.element_counts(&Exact, steps).into_iter().map(|(c, n)| (c, n.try_into().unwrap())).collect()
+    };
+
+    // NNCB -> NCNBCHB
+    assert_eq!(counts(0), vec![('B', 1), ('C', 1), ('N', 2)]);
+    assert_eq!(counts(1), vec![('B', 2), ('C', 2), ('H', 1), ('N', 2)]);
+
+    // Modular counts match the exact counts well past u64 overflow
+    const MODULUS: u64 = 1_000_000_007;
+
+    let exact = polymer.element_counts(&Exact, 500);
+    let modular = polymer.element_counts(&Modular::new(MODULUS).unwrap(), 500);
+
+    assert!(exact[&'B'].bits() > 300);
+
+    for (c, count) in exact {
+        assert_eq!(count % MODULUS, BigUint::from(modular[&c]));
+    }
+
+    // Both methods agree
+    let initial: Vec<BigUint> = polymer.initial.iter().map(|&c| BigUint::from(c)).collect();
+
+    assert_eq!(polymer.periodic(&Exact, initial.clone(), 20), polymer.stepped(&Exact, initial, 1..=20));
+
+    // Pair counts which are a multiple of the modulus still count as present
+    // NNCB -> NCNBCHB -> NBCCNBBBCBHCB
+    let counts = polymer.element_counts(&Modular::new(2).unwrap(), 2);
+    assert_eq!(counts, BTreeMap::from([('B', 0), ('C', 0), ('H', 1), ('N', 0)]));
+
+    // Huge step counts
+    let counts = polymer.element_counts(&Modular::new(MODULUS).unwrap(), 1_000_000_000_000_000);
+    assert_eq!(counts.keys().collect::<String>(), "BCHN");
+
+    assert_eq!(Polymer::new("", &subs).err(), Some("Empty polymer".to_string()));
+}
+
+#[cfg(test)]
+fn expand(template: &str, rules: &Rules, steps: u64) -> String {
+    // Apply the rules to the polymer string
+    (1..=steps).fold(template.to_string(), |polymer, step| {
+        let mut next: String = polymer.chars().take(1).collect();
+
+        for pair @ (_, c2) in polymer.chars().tuple_windows() {
+            if let Some(rule) = rules.get(&pair).iter().find(|rule| rule.fires(step)) {
+                next.extend(&rule.insert);
+            }
+
+            next.push(c2);
+        }
+
+        next
+    })
+}
+
+#[test]
+fn test_general_rules() {
+    use super::load_buf;
+    use super::matrix::Modular;
+
+    let (template, rules) = load_buf("\
+ABA
+
+AB -> BA @ %3=1, 5
+BA -> C @ 2-4
+AC -> @ 1
+CB -> AAB
+BB -> C
+CA -> B @ 7-".as_bytes()).unwrap();
+
+    let polymer = Polymer::new(&template, &rules).unwrap();
+    let arith = Modular::new(u64::MAX).unwrap();
+
+    // Agrees with expanding the string, including pairs with no rules
+    for steps in 0..12 {
+        let expanded = expand(&template, &rules, steps);
+
+        let mut expected: BTreeMap<Elem, u64> = BTreeMap::new();
+
+        for c in expanded.chars() {
+            *expected.entry(c).or_default() += 1;
+        }
+
+        assert_eq!(polymer.element_counts(&arith, steps), expected, "step {}", steps);
+
+        let initial: Vec<u64> = polymer.initial.clone();
+        assert_eq!(polymer.periodic(&arith, initial.clone(), steps), polymer.stepped(&arith, initial, 1..=steps));
+    }
+
+    // Periodic rules over many steps
+    let arith = Modular::new(1_000_000_007).unwrap();
+
+    for steps in [100, 101, 102, 1_000] {
+        let initial: Vec<u64> = polymer.initial.clone();
+
+        assert_eq!(polymer.periodic(&arith, initial.clone(), steps), polymer.stepped(&arith, initial, 1..=steps));
+    }
+    // Periods too large for a u64 are stepped instead
+    let (template, rules) = load_buf("AB\n\nAB -> A @ %4294967291\nAA -> B @ %4294967279=1\nBB -> A @ %4294967231=1\nAB -> B".as_bytes()).unwrap();
+    let polymer = Polymer::new(&template, &rules).unwrap();
+
+    assert_eq!(rules.schedule().1, None);
+    assert_eq!(polymer.element_counts(&arith, 8).values().sum::<u64>(), expand(&template, &rules, 8).len() as u64);
+}
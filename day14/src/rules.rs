@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+
+use super::{Elem, Pair};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Condition {
+    // Step numbers start at 1 for the first insertion
+    At(u64),
+    Range(u64, u64),
+    From(u64),
+    // Steps where step % period == offset
+    Every { period: u64, offset: u64 },
+}
+
+impl Condition {
+
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let number = |s: &str| s.trim().parse::<u64>().map_err(|_| format!("invalid step number '{}'", s.trim()));
+
+        let text = text.trim();
+
+        let condition = if let Some(every) = text.strip_prefix('%') {
+            // %K or %K=R
+            let (period, offset) = match every.split_once('=') {
+                Some((period, offset)) => (number(period)?, number(offset)?),
+                None => (number(every)?, 0),
+            };
+
+            if period == 0 || offset >= period {
+                return Err(format!("invalid step period '{}'", text));
+            }
+
+            Condition::Every { period, offset }
+        } else if let Some((from, to)) = text.split_once('-') {
+            // N-M or N-
+            if to.trim().is_empty() {
+                Condition::From(number(from)?)
+            } else {
+                let (from, to) = (number(from)?, number(to)?);
+
+                if to < from {
+                    return Err(format!("empty step range '{}'", text));
+                }
+
+                Condition::Range(from, to)
+            }
+        } else {
+            Condition::At(number(text)?)
+        };
+
+        Ok(condition)
+    }
+
+    pub fn matches(&self, step: u64) -> bool {
+        match *self {
+            Condition::At(at) => step == at,
+            Condition::Range(from, to) => (from..=to).contains(&step),
+            Condition::From(from) => step >= from,
+            Condition::Every { period, offset } => step % period == offset,
+        }
+    }
+
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+    pub insert: Vec<Elem>,
+    // Steps the rule fires on, any of which may match. Always fires if empty
+    pub when: Vec<Condition>,
+}
+
+impl Rule {
+
+    pub fn fires(&self, step: u64) -> bool {
+        self.when.is_empty() || self.when.iter().any(|c| c.matches(step))
+    }
+
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Rules {
+    // Rules for each pair in file order, the first to fire is used
+    rules: HashMap<Pair, Vec<Rule>>,
+}
+
+impl Rules {
+
+    pub fn add(&mut self, pair: Pair, rule: Rule) {
+        self.rules.entry(pair).or_default().push(rule);
+    }
+
+    pub fn get(&self, pair: &Pair) -> &[Rule] {
+        self.rules.get(pair).map_or(&[], |rules| &rules[..])
+    }
+
+    pub fn schedule(&self) -> (u64, Option<u64>) {
+        // After the first value the rules firing repeat with the period in the second, None if
+        // the period is too large for a u64
+        let conditions = self.rules.values().flatten().flat_map(|rule| &rule.when);
+
+        conditions.fold((0, Some(1)), |(fixed, period), condition| match *condition {
+            Condition::At(at) => (fixed.max(at), period),
+            Condition::Range(_, to) => (fixed.max(to), period),
+            Condition::From(from) => (fixed.max(from), period),
+            Condition::Every { period: every, .. } => (fixed, period.and_then(|period| lcm(period, every))),
+        })
+    }
+
+}
+
+fn lcm(a: u64, b: u64) -> Option<u64> {
+    let gcd = |mut a: u64, mut b: u64| {
+        while b != 0 {
+            (a, b) = (b, a % b);
+        }
+
+        a
+    };
+
+    (a / gcd(a, b)).checked_mul(b)
+}
+
+#[test]
+fn test_conditions() {
+    assert_eq!(Condition::parse("3"), Ok(Condition::At(3)));
+    assert_eq!(Condition::parse(" 2-5"), Ok(Condition::Range(2, 5)));
+    assert_eq!(Condition::parse("7-"), Ok(Condition::From(7)));
+    assert_eq!(Condition::parse("%2"), Ok(Condition::Every { period: 2, offset: 0 }));
+    assert_eq!(Condition::parse("%3=1"), Ok(Condition::Every { period: 3, offset: 1 }));
+
+    assert_eq!(Condition::parse("x"), Err("invalid step number 'x'".to_string()));
+    assert_eq!(Condition::parse("5-2"), Err("empty step range '5-2'".to_string()));
+    assert_eq!(Condition::parse("%3=3"), Err("invalid step period '%3=3'".to_string()));
+
+    let rule = Rule { insert: vec!['X'], when: vec![Condition::Range(2, 3), Condition::Every { period: 4, offset: 1 }] };
+    let fires: Vec<u64> = (1..=10).filter(|&s| rule.fires(s)).collect();
+
+    assert_eq!(fires, vec![1, 2, 3, 5, 9]);
+
+    let mut rules = Rules::default();
+    assert_eq!(rules.schedule(), (0, Some(1)));
+
+    rules.add(('A', 'B'), rule);
+    rules.add(('B', 'A'), Rule { insert: vec![], when: vec![Condition::From(6), Condition::Every { period: 6, offset: 0 }] });
+
+    assert_eq!(rules.schedule(), (6, Some(12)));
+    assert_eq!(rules.get(&('A', 'B')).len(), 1);
+    assert!(rules.get(&('A', 'A')).is_empty());
+
+    // Large coprime periods overflow
+    for period in [4_294_967_291, 4_294_967_279, 4_294_967_231] {
+        rules.add(('A', 'A'), Rule { insert: vec![], when: vec![Condition::Every { period, offset: 0 }] });
+    }
+
+    assert_eq!(rules.schedule(), (6, None));
+}
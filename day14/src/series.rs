@@ -0,0 +1,196 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::io::{self, Write};
+
+use super::{Elem, Pair};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SeriesFormat {
+    Csv,
+    Json,
+}
+
+impl SeriesFormat {
+
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "csv" => Ok(SeriesFormat::Csv),
+            "json" => Ok(SeriesFormat::Json),
+            _ => Err(format!("Unknown series format '{}', expected csv or json", name)),
+        }
+    }
+
+    pub fn from_file_name(file: &str) -> Self {
+        match file.rsplit_once('.').map(|(_, ext)| ext) {
+            Some("json") => SeriesFormat::Json,
+            _ => SeriesFormat::Csv,
+        }
+    }
+
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Extremes {
+    // First steps at which an element is the most and least common
+    pub most: Option<usize>,
+    pub least: Option<usize>,
+}
+
+pub struct Series<T> {
+    pub elements: Vec<Elem>,
+    pub pairs: Vec<Pair>,
+    // Counts for each step in the same order as the elements and pairs
+    pub element_counts: Vec<Vec<T>>,
+    pub pair_counts: Vec<Vec<T>>,
+}
+
+impl<T: Display + Ord + Default> Series<T> {
+
+    pub fn write<W: Write>(&self, out: &mut W, format: SeriesFormat) -> io::Result<()> {
+        match format {
+            SeriesFormat::Csv => self.write_csv(out),
+            SeriesFormat::Json => self.write_json(out),
+        }
+    }
+
+    fn write_csv<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        // Write header
+        let elements = self.elements.iter().map(|e| e.to_string());
+        let pairs = self.pairs.iter().map(|(c1, c2)| format!("{}{}", c1, c2));
+
+        writeln!(out, "step,{}", elements.chain(pairs).collect::<Vec<_>>().join(","))?;
+
+        // Write a line per step
+        for (step, (elements, pairs)) in self.element_counts.iter().zip(&self.pair_counts).enumerate() {
+            let cols: Vec<String> = elements.iter().chain(pairs).map(|c| c.to_string()).collect();
+
+            writeln!(out, "{},{}", step, cols.join(","))?;
+        }
+
+        Ok(())
+    }
+
+    fn write_json<W: Write>(&self, out: &mut W) -> io::Result<()> {
+        // Counts can exceed 64 bits so write the numbers directly
+        let object = |names: Vec<String>, counts: &[T]| -> String {
+            let fields: Vec<String> = names.into_iter().zip(counts).map(|(name, count)| format!("\"{}\":{}", name, count)).collect();
+
+            format!("{{{}}}", fields.join(","))
+        };
+
+        writeln!(out, "[")?;
+
+        for (step, (elements, pairs)) in self.element_counts.iter().zip(&self.pair_counts).enumerate() {
+            let element_names = self.elements.iter().map(|e| e.to_string()).collect();
+            let pair_names = self.pairs.iter().map(|(c1, c2)| format!("{}{}", c1, c2)).collect();
+            let sep = if step + 1 < self.element_counts.len() { "," } else { "" };
+
+            writeln!(out, "  {{\"step\":{},\"elements\":{},\"pairs\":{}}}{}",
+                step, object(element_names, elements), object(pair_names, pairs), sep)?;
+        }
+
+        writeln!(out, "]")?;
+
+        Ok(())
+    }
+
+    pub fn first_extremes(&self) -> BTreeMap<Elem, Extremes> {
+        let mut extremes: BTreeMap<Elem, Extremes> = self.elements.iter().map(|&e| (e, Extremes::default())).collect();
+        let zero = T::default();
+
+        for (step, counts) in self.element_counts.iter().enumerate() {
+            // Elements not yet in the polymer can't be the least common
+            let present: Vec<(Elem, &T)> = self.elements.iter().copied().zip(counts).filter(|(_, c)| **c > zero).collect();
+
+            let Some(max) = present.iter().map(|(_, c)| *c).max() else {
+                continue
+            };
+
+            let min = present.iter().map(|(_, c)| *c).min().unwrap();
+
+            // Ties count as most or least common
+            for &(elem, count) in &present {
+                let entry = extremes.get_mut(&elem).unwrap();
+
+                if count == max && entry.most.is_none() {
+                    entry.most = Some(step);
+                }
+
+                if count == min && entry.least.is_none() {
+                    entry.least = Some(step);
+                }
+            }
+        }
+
+        extremes
+    }
+
+}
+
+#[cfg(test)]
+fn example() -> Series<u64> {
+    use super::{load_buf, tests::TEST_INPUT};
+    use super::matrix::Modular;
+    use super::polymer::Polymer;
+
+    let (template, subs) = load_buf(TEST_INPUT.as_bytes()).unwrap();
+
+    Polymer::new(&template, &subs).unwrap().series(&Modular::new(u64::MAX).unwrap(), 10)
+}
+
+#[test]
+fn test_series() {
+    let series = example();
+
+    assert_eq!(series.elements, vec!['B', 'C', 'H', 'N']);
+    assert_eq!(series.element_counts.len(), 11);
+
+    // NNCB -> NCNBCHB -> NBCCNBBBCBHCB
+    assert_eq!(series.element_counts[0], vec![1, 1, 0, 2]);
+    assert_eq!(series.element_counts[1], vec![2, 2, 1, 2]);
+    assert_eq!(series.element_counts[2], vec![6, 4, 1, 2]);
+    assert_eq!(series.element_counts[10], vec![1749, 298, 161, 865]);
+
+    // Pair counts add up to one less than the length
+    for (elements, pairs) in series.element_counts.iter().zip(&series.pair_counts) {
+        assert_eq!(pairs.iter().sum::<u64>() + 1, elements.iter().sum::<u64>());
+    }
+
+    // N is most common at the start, B from step 1. H is least common as soon as it appears
+    let extremes = series.first_extremes();
+
+    assert_eq!(extremes[&'N'], Extremes { most: Some(0), least: None });
+    assert_eq!(extremes[&'B'], Extremes { most: Some(1), least: Some(0) });
+    assert_eq!(extremes[&'C'], Extremes { most: Some(1), least: Some(0) });
+    assert_eq!(extremes[&'H'], Extremes { most: None, least: Some(1) });
+}
+
+#[test]
+fn test_write() {
+    let mut series = example();
+
+    series.element_counts.truncate(2);
+    series.pair_counts.truncate(2);
+
+    let mut out = Vec::new();
+    series.write(&mut out, SeriesFormat::Csv).unwrap();
+
+    let csv = String::from_utf8(out).unwrap();
+    let lines: Vec<&str> = csv.lines().collect();
+
+    assert_eq!(lines.len(), 3);
+    assert!(lines[0].starts_with("step,B,C,H,N,BB,BC,"));
+    assert!(lines[1].starts_with("0,1,1,0,2,"));
+
+    let mut out = Vec::new();
+    series.write(&mut out, SeriesFormat::Json).unwrap();
+
+    let json = String::from_utf8(out).unwrap();
+
+    assert!(json.starts_with("[\n  {\"step\":0,\"elements\":{\"B\":1,\"C\":1,\"H\":0,\"N\":2},\"pairs\":{\"BB\":0,"));
+    assert!(json.ends_with("}}\n]\n"));
+    assert_eq!(json.matches("\"step\"").count(), 2);
+
+    assert_eq!(SeriesFormat::from_file_name("out.json"), SeriesFormat::Json);
+    assert!(SeriesFormat::parse("xml").is_err());
+}
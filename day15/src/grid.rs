@@ -0,0 +1,305 @@
+use super::{Coord, Coords, PathRisk, Risk};
+use super::route::Route;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Tiling {
+    // Number of copies of the map across and down
+    pub factor: usize,
+    // Risk added for each tile right or down
+    pub increment: u32,
+    // Risks wrap round to 1 after this
+    pub modulus: u32,
+}
+
+impl Default for Tiling {
+    fn default() -> Self {
+        Tiling { factor: 1, increment: 1, modulus: 9 }
+    }
+}
+
+impl Tiling {
+
+    pub fn risk(&self, base: Risk, tile: usize) -> Risk {
+        // Map risks are never above the modulus so the first tile is unchanged
+        if tile == 0 {
+            return base;
+        }
+
+        let modulus = self.modulus as u64;
+        let added = (self.increment as u64 % modulus) * (tile as u64 % modulus);
+
+        (((base as u64 + added + modulus - 1) % modulus) + 1) as Risk
+    }
+
+    #[cfg(test)]
+    pub fn expand(&self, risks: &[Vec<Risk>]) -> Vec<Vec<Risk>> {
+        // Build the full tiled map
+        (0..self.factor).flat_map(|ychunk| {
+            risks.iter().map(move |row| {
+                (0..self.factor).flat_map(|xchunk| row.iter().map(move |&val| self.risk(val, xchunk + ychunk))).collect()
+            })
+        }).collect()
+    }
+
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Moves {
+    Four,
+    Eight,
+    Knight,
+}
+
+impl Moves {
+
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "4" => Ok(Moves::Four),
+            "8" => Ok(Moves::Eight),
+            "knight" => Ok(Moves::Knight),
+            _ => Err(format!("Unknown neighbourhood '{}', expected 4, 8 or knight", name)),
+        }
+    }
+
+    fn offsets(&self) -> &'static [(isize, isize)] {
+        match self {
+            Moves::Four => &[(1, 0), (0, 1), (-1, 0), (0, -1)],
+            Moves::Eight => &[(1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0), (-1, -1), (0, -1), (1, -1)],
+            Moves::Knight => &[(1, 2), (2, 1), (2, -1), (1, -2), (-1, -2), (-2, -1), (-2, 1), (-1, 2)],
+        }
+    }
+
+    fn min_moves(&self, dx: usize, dy: usize) -> usize {
+        // Lower bound on the moves needed to cover a distance
+        match self {
+            Moves::Four => dx + dy,
+            Moves::Eight => dx.max(dy),
+            // Each move covers at most 2 in one direction and 3 in total
+            Moves::Knight => dx.max(dy).div_ceil(2).max((dx + dy).div_ceil(3)),
+        }
+    }
+
+}
+
+#[derive(Debug, Clone)]
+pub struct GridOptions {
+    pub tiling: Tiling,
+    pub moves: Moves,
+    // Defaults to the top left and bottom right
+    pub start: Option<Coords>,
+    pub end: Option<Coords>,
+}
+
+impl Default for GridOptions {
+    fn default() -> Self {
+        GridOptions { tiling: Tiling::default(), moves: Moves::Four, start: None, end: None }
+    }
+}
+
+// Route finding needs 9 bytes of state per cell (distance, previous cell and a done flag),
+// so this keeps the working memory to a little over 1GB
+pub const MAX_CELLS: usize = 1 << 27;
+
+pub struct Grid {
+    // Risks of a single tile, row by row. Other tiles are calculated when needed
+    tile: Vec<Risk>,
+    tile_width: usize,
+    tile_height: usize,
+    tiling: Tiling,
+    moves: Moves,
+    width: usize,
+    height: usize,
+    start: usize,
+    end: usize,
+    min_risk: Risk,
+    max_risk: Risk,
+}
+
+impl Grid {
+
+    pub fn new(risks: &[Vec<Risk>], options: &GridOptions) -> Result<Self, String> {
+        let tiling = options.tiling;
+
+        if tiling.factor == 0 || tiling.modulus == 0 || tiling.modulus > Risk::MAX as u32 {
+            return Err(format!("Invalid tiling {:?}", tiling));
+        }
+
+        let tile_width = risks.first().map_or(0, |row| row.len());
+        let tile_height = risks.len();
+
+        if tile_width == 0 || risks.iter().any(|row| row.len() != tile_width) {
+            return Err("Risk map must be a non-empty rectangle".to_string());
+        }
+
+        if let Some(&risk) = risks.iter().flatten().find(|&&risk| risk as u32 > tiling.modulus) {
+            return Err(format!("Risk {} is above the wrap modulus {}", risk, tiling.modulus));
+        }
+
+        let (width, height) = match (tile_width.checked_mul(tiling.factor), tile_height.checked_mul(tiling.factor)) {
+            (Some(width), Some(height)) if width.checked_mul(height).is_some_and(|cells| cells <= MAX_CELLS) => (width, height),
+            _ => return Err(format!("Tiling factor {} makes the map larger than {} cells", tiling.factor, MAX_CELLS)),
+        };
+
+        let tile: Vec<Risk> = risks.iter().flatten().copied().collect();
+
+        // Range of risks, only the first few tile offsets can differ
+        let offsets = (2 * tiling.factor - 1).min(tiling.modulus as usize + 1);
+        let all_risks = || (0..offsets).flat_map(|offset| tile.iter().map(move |&r| tiling.risk(r, offset)));
+        let (min_risk, max_risk) = (all_risks().min().unwrap(), all_risks().max().unwrap());
+
+        let cell = |pos: Option<Coords>, default: Coords| -> Result<usize, String> {
+            let (x, y) = pos.unwrap_or(default);
+
+            if x as usize >= width || y as usize >= height {
+                return Err(format!("Position {},{} is outside the {}x{} map", x, y, width, height));
+            }
+
+            Ok(y as usize * width + x as usize)
+        };
+
+        Ok(Grid {
+            start: cell(options.start, (0, 0))?,
+            end: cell(options.end, ((width - 1) as Coord, (height - 1) as Coord))?,
+            min_risk,
+            max_risk,
+            tile,
+            tile_width,
+            tile_height,
+            tiling,
+            moves: options.moves,
+            width,
+            height,
+        })
+    }
+
+    pub fn cells(&self) -> usize {
+        self.width * self.height
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    pub fn max_risk(&self) -> Risk {
+        self.max_risk
+    }
+
+    pub fn risk(&self, cell: usize) -> Risk {
+        let (x, y) = (cell % self.width, cell / self.width);
+        let base = self.tile[(y % self.tile_height) * self.tile_width + (x % self.tile_width)];
+
+        self.tiling.risk(base, x / self.tile_width + y / self.tile_height)
+    }
+
+    pub fn neighbours(&self, cell: usize) -> impl Iterator<Item = usize> + '_ {
+        let (x, y) = ((cell % self.width) as isize, (cell / self.width) as isize);
+
+        self.moves.offsets().iter().filter_map(move |&(dx, dy)| {
+            let (nx, ny) = (x + dx, y + dy);
+
+            if nx < 0 || ny < 0 || nx as usize >= self.width || ny as usize >= self.height {
+                None
+            } else {
+                Some(ny as usize * self.width + nx as usize)
+            }
+        })
+    }
+
+    pub fn min_risk_to_end(&self, cell: usize) -> PathRisk {
+        // Fewest moves needed scaled by the lowest risk never overestimates
+        let (x1, y1) = (cell % self.width, cell / self.width);
+        let (x2, y2) = (self.end % self.width, self.end / self.width);
+
+        self.moves.min_moves(x1.abs_diff(x2), y1.abs_diff(y2)) as PathRisk * self.min_risk as PathRisk
+    }
+
+    pub fn route(&self, dist: &[PathRisk], prev: &[u32]) -> Route {
+        // Walk backwards from the end
+        let mut cells = vec![self.end];
+
+        while let Some(&cell) = cells.last() {
+            if cell == self.start {
+                break
+            }
+
+            cells.push(prev[cell] as usize);
+        }
+
+        let path = cells.into_iter().rev()
+            .map(|cell| (((cell % self.width) as Coord, (cell / self.width) as Coord), dist[cell]))
+            .collect();
+
+        Route { path }
+    }
+
+}
+
+#[test]
+fn test_tiling() {
+    let risks = vec![vec![8, 9], vec![1, 2]];
+
+    let tiling = Tiling { factor: 2, ..Default::default() };
+    assert_eq!(tiling.expand(&risks), vec![
+        vec![8, 9, 9, 1],
+        vec![1, 2, 2, 3],
+        vec![9, 1, 1, 2],
+        vec![2, 3, 3, 4],
+    ]);
+
+    // Virtual tiles match the expanded map
+    let tiling = Tiling { factor: 7, increment: 4, modulus: 5 };
+    assert_eq!(Grid::new(&risks, &GridOptions { tiling, ..Default::default() }).err(),
+        Some("Risk 8 is above the wrap modulus 5".to_string()));
+
+    let risks = vec![vec![4, 5], vec![1, 2]];
+    let expanded = tiling.expand(&risks);
+    let grid = Grid::new(&risks, &GridOptions { tiling, ..Default::default() }).unwrap();
+
+    let flat: Vec<Risk> = (0..grid.cells()).map(|cell| grid.risk(cell)).collect();
+    assert_eq!(flat, expanded.concat());
+    assert_eq!((grid.min_risk, grid.max_risk), (1, 5));
+
+    // Huge increments don't overflow
+    let tiling = Tiling { factor: 1000, increment: u32::MAX, modulus: 9 };
+    assert_eq!(tiling.risk(5, 999), ((5 + (u32::MAX as u64 % 9) * 999 + 8) % 9 + 1) as Risk);
+
+    assert!(Grid::new(&risks, &GridOptions { tiling: Tiling { modulus: 0, ..Default::default() }, ..Default::default() }).is_err());
+    assert!(Grid::new(&risks, &GridOptions { tiling: Tiling { factor: 10_000, ..Default::default() }, ..Default::default() }).is_err());
+}
+
+#[test]
+fn test_moves() {
+    let risks = vec![vec![1; 5]; 5];
+
+    let options = |moves| GridOptions { moves, start: Some((2, 2)), end: Some((4, 0)), ..Default::default() };
+
+    let grid = Grid::new(&risks, &options(Moves::Four)).unwrap();
+    assert_eq!(grid.neighbours(grid.start()).count(), 4);
+    assert_eq!(grid.neighbours(0).collect::<Vec<_>>(), vec![1, 5]);
+    assert_eq!(grid.min_risk_to_end(grid.start()), 4);
+
+    let grid = Grid::new(&risks, &options(Moves::Eight)).unwrap();
+    assert_eq!(grid.neighbours(grid.start()).count(), 8);
+    assert_eq!(grid.min_risk_to_end(grid.start()), 2);
+
+    let grid = Grid::new(&risks, &options(Moves::Knight)).unwrap();
+    assert_eq!(grid.neighbours(grid.start()).count(), 8);
+    assert_eq!(grid.neighbours(0).collect::<Vec<_>>(), vec![11, 7]);
+    assert_eq!(grid.min_risk_to_end(grid.start()), 2);
+
+    assert_eq!(Grid::new(&risks, &GridOptions { end: Some((5, 0)), ..Default::default() }).err(),
+        Some("Position 5,0 is outside the 5x5 map".to_string()));
+    assert!(Moves::parse("6").is_err());
+}
@@ -1,117 +1,147 @@
-use std::collections::HashMap;
-use std::cmp::Reverse;
+mod grid;
+mod pathfind;
+mod render;
+mod route;
+
 use std::io::{BufRead, BufReader};
 use std::error::Error;
 use std::fs::File;
+use std::time::Instant;
 use memmap2::Mmap;
-use priority_queue::PriorityQueue;
+
+use grid::{Grid, GridOptions, Moves, Tiling};
+use pathfind::{Strategy, STRATEGIES};
+use route::Route;
 
 type Risk = u8;
 type PathRisk = u32;
-type Coord = u16;
+type Coord = u32;
 type Coords = (Coord, Coord);
 
 fn main() -> Result<(), Box<dyn Error>> {
+    // Process options
+    let mut input = "input15.txt".to_string();
+    let mut show_part = 1;
+    let mut overlay = false;
+    let mut png_file = None;
+    let mut scale = 1;
+    let mut strategy: &dyn Strategy = &pathfind::Dijkstra;
+    let mut bench = false;
+    let mut options = GridOptions::default();
+    let mut tiling = Tiling { factor: 5, ..Default::default() };
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--input" => input = args.next().ok_or("--input expects a file name")?,
+            "--part" => {
+                show_part = match args.next().as_deref() {
+                    Some("1") => 1,
+                    Some("2") => 2,
+                    _ => return Err("--part expects 1 or 2".into()),
+                }
+            }
+            "--overlay" => overlay = true,
+            "--png" => png_file = Some(args.next().ok_or("--png expects a file name")?),
+            "--scale" => {
+                scale = args.next().ok_or("--scale expects a factor")?.parse()?;
+
+                if scale == 0 {
+                    return Err("--scale must be at least 1".into());
+                }
+            }
+            "--strategy" => strategy = pathfind::strategy(&args.next().ok_or("--strategy expects dijkstra, astar or dial")?)?,
+            "--bench" => bench = true,
+            "--tiles" => tiling.factor = args.next().ok_or("--tiles expects a tiling factor")?.parse()?,
+            "--increment" => tiling.increment = args.next().ok_or("--increment expects a risk increment")?.parse()?,
+            "--wrap" => tiling.modulus = args.next().ok_or("--wrap expects a wrap modulus")?.parse()?,
+            "--neighbourhood" => options.moves = Moves::parse(&args.next().ok_or("--neighbourhood expects 4, 8 or knight")?)?,
+            "--start" => options.start = Some(parse_coords(&args.next().ok_or("--start expects x,y")?)?),
+            "--end" => options.end = Some(parse_coords(&args.next().ok_or("--end expects x,y")?)?),
+            _ => return Err(format!("Unrecognised argument '{}'", arg).into()),
+        }
+    }
+
     // Load the input file
-    let risks = load_input("input15.txt")?;
+    let risks = load_input(&input)?;
 
-    // Run parts
-    part1(&risks);
+    // Run parts. Part 2 tiles the map, and positions only apply to the chosen part
+    let corners = GridOptions { start: None, end: None, ..options.clone() };
 
-    let risks2 = expand_map(&risks);
-    part2(&risks2);
+    let (options, options2) = if show_part == 1 {
+        (options, GridOptions { tiling, ..corners })
+    } else {
+        (corners, GridOptions { tiling, ..options })
+    };
 
-    Ok(())
-}
+    let route1 = part1(&risks, &options, strategy)?;
+    let route2 = part2(&risks, &options2, strategy)?;
 
-fn part1(risks: &[Vec<Risk>]) {
-    let lowest_risk = find_lowest_risk(risks);
+    if bench {
+        benchmark(&risks, &options2)?;
+    }
 
-    println!("Part 1: Lowest risk: {}", lowest_risk);
-}
+    if !overlay && png_file.is_none() {
+        return Ok(());
+    }
 
-fn part2(risks: &[Vec<Risk>]) {
-    let lowest_risk = find_lowest_risk(risks);
+    // Show the chosen route on the full map
+    let (grid, route) = if show_part == 1 {
+        (Grid::new(&risks, &options)?, route1)
+    } else {
+        (Grid::new(&risks, &options2)?, route2)
+    };
 
-    println!("Part 2: Lowest risk: {}", lowest_risk);
-}
+    if overlay {
+        print!("{}", route.overlay(&grid));
+    }
 
-fn neighbours(x: Coord, y: Coord, max_x: Coord, max_y: Coord) -> impl Iterator<Item = Coords> {
-    let mut num = 0;
+    if let Some(file) = png_file {
+        render::gen_route_png(&grid, &route, scale, &file)?;
 
-    std::iter::from_fn(move || {
-        loop {
-            num += 1;
+        println!("Route written to {}", file);
+    }
 
-            match num {
-                1 => if x < max_x { return Some((x + 1, y)) }
-                2 => if y < max_y { return Some((x, y + 1)); }
-                3 => if x > 0 { return Some((x - 1, y)); }
-                4 => if y > 0 { return Some((x, y - 1)); }
-                _ => return None
-            }
-        }
-    })
+    Ok(())
 }
 
-fn find_lowest_risk(risks: &[Vec<Risk>]) -> PathRisk {
-    let width = risks[0].len();
-    let height = risks.len();
-    let max_x = (width - 1) as Coord;
-    let max_y = (height - 1) as Coord;
-
-    let mut prev: HashMap<Coords, Coords> = HashMap::with_capacity(width * height);
-    let mut queue: PriorityQueue<Coords, Reverse<PathRisk>> = PriorityQueue::with_capacity(width + height);
+fn part1(risks: &[Vec<Risk>], options: &GridOptions, strategy: &dyn Strategy) -> Result<Route, Box<dyn Error>> {
+    let route = find_lowest_risk(risks, options, strategy)?;
 
-    queue.push((0, 0), Reverse(0));
-    prev.insert((0, 0), (Coord::MAX, Coord::MAX));
+    println!("Part 1: Lowest risk: {} ({} steps)", route.risk(), route.path.len() - 1);
 
-    while let Some((item1 @ (x1, y1), Reverse(dist))) = queue.pop() {
-        for item2 @ (x2, y2) in neighbours(x1, y1, max_x, max_y) {
-            if prev.get(&item2).is_none() {
-                let calc_dist = dist + risks[y2 as usize][x2 as usize] as PathRisk;
-                queue.push(item2, Reverse(calc_dist));
-                prev.insert(item2, item1);
-            }
-        }
-    }
+    Ok(route)
+}
 
-    // Walk backwards
-    let mut risk: PathRisk = 0;
+fn part2(risks: &[Vec<Risk>], options: &GridOptions, strategy: &dyn Strategy) -> Result<Route, Box<dyn Error>> {
+    let route = find_lowest_risk(risks, options, strategy)?;
 
-    let mut pos = (max_x, max_y);
+    println!("Part 2: Lowest risk: {} ({} steps)", route.risk(), route.path.len() - 1);
 
-    loop {
-        let (x, y) = pos;
+    Ok(route)
+}
 
-        if x == 0 && y == 0 {
-            break
-        }
+fn benchmark(risks: &[Vec<Risk>], options: &GridOptions) -> Result<(), String> {
+    let grid = Grid::new(risks, options)?;
 
-        risk += risks[y as usize][x as usize] as PathRisk;
+    for strategy in STRATEGIES {
+        let start = Instant::now();
+        let result = strategy.find_route(&grid).map_or("no route".to_string(), |route| format!("risk {}", route.risk()));
 
-        pos = *prev.get(&pos).unwrap();
+        println!("{:>10}: {} in {:.2?}", strategy.name(), result, start.elapsed());
     }
 
-    risk
+    Ok(())
 }
 
-fn expand_map(risks: &[Vec<Risk>]) -> Vec<Vec<Risk>> {
-    let mut new_map = Vec::with_capacity(risks.len() * 5);
+fn find_lowest_risk(risks: &[Vec<Risk>], options: &GridOptions, strategy: &dyn Strategy) -> Result<Route, String> {
+    strategy.find_route(&Grid::new(risks, options)?).ok_or_else(|| "No route found".to_string())
+}
 
-    for ychunk in 0..5 {
-        for srcline in risks {
-            let mut new_line = Vec::with_capacity(srcline.len() * 5);
-            for xchunk in 0..5 {
-                for val in srcline {
-                    new_line.push((((val + xchunk + ychunk) - 1) % 9) + 1);
-                }
-            }
-            new_map.push(new_line);
-        }
-    }
+fn parse_coords(text: &str) -> Result<Coords, String> {
+    let coords = text.split_once(',').and_then(|(x, y)| Some((x.trim().parse().ok()?, y.trim().parse().ok()?)));
 
-    new_map
+    coords.ok_or_else(|| format!("Invalid position '{}', expected x,y", text))
 }
 
 type ParseResult = Vec<Vec<Risk>>;
@@ -224,17 +254,35 @@ mod tests {
         let risks1 = load_buf(TEST_INPUT.as_bytes()).unwrap();
         let risks2 = load_buf(EXPANDED_INPUT.as_bytes()).unwrap();
 
-        let lowest_risk = find_lowest_risk(&risks1);
+        let options = GridOptions::default();
+        let route = find_lowest_risk(&risks1, &options, &pathfind::Dijkstra).unwrap();
+
+        assert_eq!(route.risk(), 40);
+        assert_eq!(route.path.first(), Some(&((0, 0), 0)));
+        assert_eq!(route.path.last(), Some(&((9, 9), 40)));
+
+        // Each step moves to a neighbour
+        for pair in route.path.windows(2) {
+            let (((x1, y1), _), ((x2, y2), _)) = (pair[0], pair[1]);
+
+            assert_eq!(x1.abs_diff(x2) + y1.abs_diff(y2), 1);
+        }
+
+        assert_eq!(route.overlay(&Grid::new(&risks1, &options).unwrap()).lines().next(), Some("*163751742"));
 
-        assert_eq!(lowest_risk, 40);
+        let tiling = Tiling { factor: 5, ..Default::default() };
 
-        let test_risks2 = expand_map(&risks1);
+        assert_eq!(risks2, tiling.expand(&risks1));
 
-        assert_eq!(risks2, test_risks2);
+        // Virtual tiling finds the same route as the expanded map
+        let route = find_lowest_risk(&risks2, &options, &pathfind::Dijkstra).unwrap();
+        assert_eq!(route.risk(), 315);
 
-        let lowest_risk = find_lowest_risk(&risks2);
+        let options2 = GridOptions { tiling, ..Default::default() };
+        assert_eq!(find_lowest_risk(&risks1, &options2, &pathfind::Dijkstra), Ok(route));
 
-        assert_eq!(lowest_risk, 315);
+        assert_eq!(parse_coords("3, 4"), Ok((3, 4)));
+        assert!(parse_coords("3").is_err());
     }
 
 }
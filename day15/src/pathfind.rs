@@ -0,0 +1,216 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use super::PathRisk;
+use super::grid::Grid;
+use super::route::Route;
+
+const NONE: u32 = u32::MAX;
+
+pub trait Strategy {
+    fn name(&self) -> &'static str;
+
+    // Lowest risk route from the start to the end
+    fn find_route(&self, grid: &Grid) -> Option<Route>;
+}
+
+pub struct Dijkstra;
+
+impl Strategy for Dijkstra {
+    fn name(&self) -> &'static str {
+        "dijkstra"
+    }
+
+    fn find_route(&self, grid: &Grid) -> Option<Route> {
+        AStar::search(grid, |_| 0)
+    }
+}
+
+pub struct AStar;
+
+impl AStar {
+    fn search(grid: &Grid, heuristic: impl Fn(usize) -> PathRisk) -> Option<Route> {
+        let mut dist = vec![PathRisk::MAX; grid.cells()];
+        let mut prev = vec![NONE; grid.cells()];
+        let mut done = vec![false; grid.cells()];
+        let mut queue = BinaryHeap::new();
+
+        dist[grid.start()] = 0;
+        queue.push(Reverse((heuristic(grid.start()), grid.start())));
+
+        while let Some(Reverse((_, cell))) = queue.pop() {
+            // Cells are finished when first popped, later entries are stale
+            if done[cell] {
+                continue
+            }
+
+            done[cell] = true;
+
+            if cell == grid.end() {
+                return Some(grid.route(&dist, &prev));
+            }
+
+            for next in grid.neighbours(cell) {
+                let next_dist = dist[cell] + grid.risk(next) as PathRisk;
+
+                if next_dist < dist[next] {
+                    dist[next] = next_dist;
+                    prev[next] = cell as u32;
+                    queue.push(Reverse((next_dist + heuristic(next), next)));
+                }
+            }
+        }
+
+        None
+    }
+}
+
+impl Strategy for AStar {
+    fn name(&self) -> &'static str {
+        "astar"
+    }
+
+    fn find_route(&self, grid: &Grid) -> Option<Route> {
+        Self::search(grid, |cell| grid.min_risk_to_end(cell))
+    }
+}
+
+pub struct Dial;
+
+impl Strategy for Dial {
+    fn name(&self) -> &'static str {
+        "dial"
+    }
+
+    fn find_route(&self, grid: &Grid) -> Option<Route> {
+        let mut dist = vec![PathRisk::MAX; grid.cells()];
+        let mut prev = vec![NONE; grid.cells()];
+
+        // Pending cells never differ in distance by more than the maximum risk, so
+        // a ring of buckets indexed by distance covers them all
+        let ring = grid.max_risk() as usize + 1;
+        let mut buckets: Vec<Vec<u32>> = vec![Vec::new(); ring];
+        let mut pending = 1;
+        let mut current: PathRisk = 0;
+
+        dist[grid.start()] = 0;
+        buckets[0].push(grid.start() as u32);
+
+        while pending > 0 {
+            let slot = current as usize % ring;
+
+            while let Some(cell) = buckets[slot].pop() {
+                let cell = cell as usize;
+                pending -= 1;
+
+                // Skip entries superseded by a lower distance
+                if dist[cell] != current {
+                    continue
+                }
+
+                if cell == grid.end() {
+                    return Some(grid.route(&dist, &prev));
+                }
+
+                for next in grid.neighbours(cell) {
+                    let next_dist = current + grid.risk(next) as PathRisk;
+
+                    if next_dist < dist[next] {
+                        dist[next] = next_dist;
+                        prev[next] = cell as u32;
+                        buckets[next_dist as usize % ring].push(next as u32);
+                        pending += 1;
+                    }
+                }
+            }
+
+            current += 1;
+        }
+
+        None
+    }
+}
+
+pub const STRATEGIES: [&dyn Strategy; 3] = [&Dijkstra, &AStar, &Dial];
+
+pub fn strategy(name: &str) -> Result<&'static dyn Strategy, String> {
+    STRATEGIES.iter().find(|s| s.name() == name).copied()
+        .ok_or_else(|| format!("Unknown strategy '{}', expected dijkstra, astar or dial", name))
+}
+
+#[test]
+fn test_agreement() {
+    use super::{Coord, Risk};
+    use super::grid::{GridOptions, Moves, Tiling};
+
+    // Grids from a simple generator, including a zero risk cell
+    let mut seed: u32 = 12345;
+
+    let mut rand = |n: u32| {
+        seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+        (seed >> 16) % n
+    };
+
+    for size in [1, 2, 5, 20, 60] {
+        let mut risks: Vec<Vec<Risk>> = (0..size).map(|_| (0..size + 3).map(|_| rand(9) as Risk + 1).collect()).collect();
+        risks[size / 2][1] = 0;
+
+        for (moves, steps) in [(Moves::Four, vec![1]), (Moves::Eight, vec![1, 2]), (Moves::Knight, vec![3])] {
+            let options = GridOptions { moves, ..Default::default() };
+            let grid = Grid::new(&risks, &options).unwrap();
+
+            let routes: Vec<Option<Route>> = STRATEGIES.iter().map(|s| s.find_route(&grid)).collect();
+
+            for route in &routes {
+                assert_eq!(route.is_some(), routes[0].is_some(), "size {} {:?}", size, moves);
+
+                let Some(route) = route else {
+                    continue
+                };
+
+                assert_eq!(route.risk(), routes[0].as_ref().unwrap().risk(), "size {} {:?}", size, moves);
+                assert_eq!(route.path.first().unwrap().0, (0, 0));
+                assert_eq!(route.path.last().unwrap().0, (size as Coord + 2, size as Coord - 1));
+
+                // Cumulative risks follow the path
+                for pair in route.path.windows(2) {
+                    let (((x1, y1), r1), ((x2, y2), r2)) = (pair[0], pair[1]);
+
+                    assert!(steps.contains(&(x1.abs_diff(x2) + y1.abs_diff(y2))));
+                    assert_eq!(r2 - r1, risks[y2 as usize][x2 as usize] as PathRisk);
+                }
+            }
+        }
+    }
+
+    // A* with every cell costing at least 2
+    let risks = vec![vec![2, 9, 2, 2], vec![2, 2, 2, 9], vec![9, 9, 3, 2]];
+    let grid = Grid::new(&risks, &GridOptions::default()).unwrap();
+
+    assert_eq!(grid.min_risk_to_end(0), 10);
+    assert_eq!(AStar.find_route(&grid).unwrap().risk(), Dijkstra.find_route(&grid).unwrap().risk());
+
+    // Any start and end, on a virtually tiled map
+    let options = GridOptions {
+        tiling: Tiling { factor: 3, increment: 2, modulus: 11 },
+        moves: Moves::Eight,
+        start: Some((11, 1)),
+        end: Some((0, 8)),
+    };
+    let grid = Grid::new(&risks, &options).unwrap();
+
+    for strategy in STRATEGIES {
+        let route = strategy.find_route(&grid).unwrap();
+
+        assert_eq!(route.path.first().unwrap().0, (11, 1));
+        assert_eq!(route.path.last().unwrap().0, (0, 8));
+        assert_eq!(route.risk(), Dijkstra.find_route(&grid).unwrap().risk());
+    }
+
+    // Knights can't move on a 2x2 map
+    let grid = Grid::new(&[vec![1, 1], vec![1, 1]], &GridOptions { moves: Moves::Knight, ..Default::default() }).unwrap();
+    assert!(STRATEGIES.iter().all(|s| s.find_route(&grid).is_none()));
+
+    assert_eq!(strategy("astar").unwrap().name(), "astar");
+    assert!(strategy("bfs").is_err());
+}
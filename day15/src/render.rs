@@ -0,0 +1,68 @@
+use std::error::Error;
+use std::fs::File;
+use std::collections::HashSet;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use super::Risk;
+use super::grid::Grid;
+use super::route::Route;
+
+const ROUTE_COLOUR: [u8; 3] = [0xe0, 0x20, 0x20];
+
+fn risk_colour(risk: Risk) -> [u8; 3] {
+    // Light for low risk through to dark for high risk
+    let level = 0xf0 - (risk.clamp(1, 9) - 1) * 0x18;
+
+    [level, level, level]
+}
+
+pub fn gen_route_png(grid: &Grid, route: &Route, scale: usize, filename: &str) -> Result<(), Box<dyn Error>> {
+    let (width, height) = match (grid.width().checked_mul(scale), grid.height().checked_mul(scale)) {
+        (Some(width), Some(height)) if width <= u32::MAX as usize && height <= u32::MAX as usize => (width as u32, height as u32),
+        _ => return Err("Route image is too large for a PNG".into()),
+    };
+
+    let on_route: HashSet<usize> = route.path.iter().map(|&((x, y), _)| y as usize * grid.width() + x as usize).collect();
+
+    let file = File::create(Path::new(filename))?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header()?;
+    let mut stream = writer.stream_writer()?;
+
+    // Build one row at a time from the grid so the full map is never held in memory
+    for y in 0..grid.height() {
+        let row: Vec<u8> = (0..grid.width()).flat_map(|x| {
+            let cell = y * grid.width() + x;
+            let colour = if on_route.contains(&cell) { ROUTE_COLOUR } else { risk_colour(grid.risk(cell)) };
+
+            // Repeat each pixel scale times across
+            std::iter::repeat_n(colour, scale).flatten()
+        }).collect();
+
+        // and down
+        for _ in 0..scale {
+            stream.write_all(&row)?;
+        }
+    }
+
+    stream.finish()?;
+
+    Ok(())
+}
+
+#[test]
+fn test_colours() {
+    // Risk levels are distinct greys and never clash with the route
+    let colours: Vec<[u8; 3]> = (1..=9).map(risk_colour).collect();
+
+    for pair in colours.windows(2) {
+        assert!(pair[0][0] > pair[1][0]);
+    }
+
+    assert!(colours.iter().all(|&c| c != ROUTE_COLOUR));
+}
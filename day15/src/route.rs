@@ -0,0 +1,49 @@
+use std::collections::HashSet;
+
+use super::{Coords, PathRisk};
+use super::grid::Grid;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Route {
+    // Each cell on the route with the total risk on entering it
+    pub path: Vec<(Coords, PathRisk)>,
+}
+
+impl Route {
+
+    pub fn risk(&self) -> PathRisk {
+        self.path.last().map_or(0, |&(_, risk)| risk)
+    }
+
+    pub fn overlay(&self, grid: &Grid) -> String {
+        // Risk map with the route marked, read from the grid so tiles are never expanded
+        let on_route: HashSet<Coords> = self.path.iter().map(|&(pos, _)| pos).collect();
+
+        let mut text = String::with_capacity((grid.width() + 1) * grid.height());
+
+        for y in 0..grid.height() {
+            for x in 0..grid.width() {
+                let risk = grid.risk(y * grid.width() + x);
+
+                // Risks above 9 from wider wrapping use letters
+                text.push(if on_route.contains(&(x as u32, y as u32)) { '*' } else { char::from_digit(risk as u32, 36).unwrap_or('+') });
+            }
+
+            text.push('\n');
+        }
+
+        text
+    }
+
+}
+
+#[test]
+fn test_route() {
+    let risks = vec![vec![1, 9, 9], vec![1, 1, 9], vec![9, 2, 3]];
+    let route = Route { path: vec![((0, 0), 0), ((0, 1), 1), ((1, 1), 2), ((1, 2), 4), ((2, 2), 7)] };
+
+    assert_eq!(route.risk(), 7);
+    let grid = Grid::new(&risks, &Default::default()).unwrap();
+
+    assert_eq!(route.overlay(&grid), "*99\n**9\n9**\n");
+}
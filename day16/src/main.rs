@@ -1,12 +1,26 @@
 mod load;
 mod parser;
 mod packet;
+mod writer;
 
 use std::error::Error;
 
 fn main() -> Result<(), Box<dyn Error>> {
+    // Process options
+    let mut input = "input16.txt".to_string();
+    let mut encode = None;
+    let mut args = std::env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--input" => input = args.next().ok_or("--input expects a file name")?,
+            "--encode" => encode = Some(packet::LengthPolicy::parse(&args.next().ok_or("--encode expects bits, count or alternate")?)?),
+            _ => return Err(format!("Unrecognised argument '{}'", arg).into()),
+        }
+    }
+
     // Load the input file
-    let data = load::load_input("input16.txt")?;
+    let data = load::load_input(&input)?;
 
     // Build tree
     let tree = packet::parse_data(&data);
@@ -19,6 +33,11 @@ fn main() -> Result<(), Box<dyn Error>> {
     part1(&tree);
     part2(&tree);
 
+    // Encode the tree again
+    if let Some(policy) = encode {
+        println!("Encoded: {}", tree.encode(policy)?);
+    }
+
     Ok(())
 }
 
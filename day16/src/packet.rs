@@ -1,4 +1,5 @@
 use super::parser::Parser;
+use super::writer::BitWriter;
 
 pub fn parse_data(data: &[u8]) -> Packet {
     let mut parser = Parser::new(data);
@@ -130,6 +131,57 @@ impl std::fmt::Display for PacketType {
 
 }
 
+impl PacketType {
+
+    fn type_id(&self) -> u8 {
+        match self {
+            PacketType::Sum(_) => 0,
+            PacketType::Product(_) => 1,
+            PacketType::Min(_) => 2,
+            PacketType::Max(_) => 3,
+            PacketType::Literal(_) => 4,
+            PacketType::Gt(_) => 5,
+            PacketType::Lt(_) => 6,
+            PacketType::Eq(_) => 7,
+        }
+    }
+
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LengthPolicy {
+    // Length type 0, total length of sub-packets in bits
+    Bits,
+    // Length type 1, number of sub-packets
+    Count,
+    // Switch between the two at each level, starting with bits
+    Alternate,
+}
+
+impl LengthPolicy {
+
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "bits" => Ok(LengthPolicy::Bits),
+            "count" => Ok(LengthPolicy::Count),
+            "alternate" => Ok(LengthPolicy::Alternate),
+            _ => Err(format!("Unknown length policy '{}', expected bits, count or alternate", name)),
+        }
+    }
+
+    fn length_type(&self, depth: usize, sub_packets: usize) -> u8 {
+        let length_type = match self {
+            LengthPolicy::Bits => 0,
+            LengthPolicy::Count => 1,
+            LengthPolicy::Alternate => (depth % 2) as u8,
+        };
+
+        // Too many sub-packets to count in 11 bits
+        if sub_packets > 0x7ff { 0 } else { length_type }
+    }
+
+}
+
 #[derive(Debug, PartialEq)]
 pub struct Packet {
     pub version: u8,
@@ -169,6 +221,80 @@ impl Packet {
         }
     }
 
+    pub fn encode(&self, policy: LengthPolicy) -> Result<String, String> {
+        let mut writer = BitWriter::new();
+
+        self.write(&mut writer, policy, 0)?;
+
+        Ok(writer.to_hex())
+    }
+
+    fn write(&self, writer: &mut BitWriter, policy: LengthPolicy, depth: usize) -> Result<(), String> {
+        if self.version > 7 {
+            return Err(format!("Version {} does not fit in 3 bits", self.version));
+        }
+
+        writer.put_bits(self.version, 3);
+        writer.put_bits(self.content.type_id(), 3);
+
+        match &self.content {
+            PacketType::Literal(num) => {
+                if *num < 0 {
+                    return Err(format!("Negative literal {} can't be encoded", num));
+                }
+
+                // Groups of 4 bits, each flagged if more follow
+                let groups = (EvalNum::BITS - num.leading_zeros()).div_ceil(4).max(1);
+
+                for group in (0..groups).rev() {
+                    let more = if group > 0 { 0x10 } else { 0 };
+
+                    writer.put_bits(more | ((num >> (group * 4)) & 0x0f) as u8, 5);
+                }
+            }
+            PacketType::Sum(values) |
+            PacketType::Product(values) |
+            PacketType::Min(values) |
+            PacketType::Max(values) |
+            PacketType::Gt(values) |
+            PacketType::Lt(values) |
+            PacketType::Eq(values) => {
+                let length_type_id = policy.length_type(depth, values.len());
+
+                writer.put_bits(length_type_id, 1);
+
+                match length_type_id {
+                    0 => {
+                        // Fill in the length once the sub-packets are written
+                        let len_pos = writer.get_pos();
+                        writer.put_bits(0u16, 15);
+
+                        for value in values {
+                            value.write(writer, policy, depth + 1)?;
+                        }
+
+                        let tot_len = writer.get_pos() - len_pos - 15;
+
+                        if tot_len > 0x7fff {
+                            return Err(format!("Sub-packets of {} bits don't fit in a 15 bit length", tot_len));
+                        }
+
+                        writer.set_bits(len_pos, tot_len as u16, 15);
+                    }
+                    _ => {
+                        writer.put_bits(values.len() as u16, 11);
+
+                        for value in values {
+                            value.write(writer, policy, depth + 1)?;
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
 }
 
 impl std::fmt::Display for Packet {
@@ -341,6 +467,44 @@ mod tests {
         assert_eq!(tree.sum_versions(), 31);
     }
 
+    #[test]
+    fn test_encode() {
+        let inputs = [
+            TEST_INPUT1_1, TEST_INPUT1_2, TEST_INPUT1_3, TEST_INPUT1_4, TEST_INPUT1_5, TEST_INPUT1_6, TEST_INPUT1_7,
+            TEST_INPUT2_1, TEST_INPUT2_2, TEST_INPUT2_3, TEST_INPUT2_4, TEST_INPUT2_5, TEST_INPUT2_6, TEST_INPUT2_7, TEST_INPUT2_8,
+        ];
+
+        // Trees survive encoding with every policy
+        for input in inputs {
+            let tree = parse_data(&load::load_buf(input.as_bytes()).unwrap());
+
+            for policy in [LengthPolicy::Bits, LengthPolicy::Count, LengthPolicy::Alternate] {
+                let hex = tree.encode(policy).unwrap();
+
+                assert_eq!(parse_data(&load::load_buf(hex.as_bytes()).unwrap()), tree, "{} {:?}", input, policy);
+            }
+        }
+
+        // Same bits as the examples, without the trailing padding
+        let encode = |input: &str, policy| parse_data(&load::load_buf(input.as_bytes()).unwrap()).encode(policy).unwrap();
+
+        assert_eq!(encode(TEST_INPUT1_1, LengthPolicy::Bits), "D2FE28");
+        assert_eq!(encode(TEST_INPUT1_2, LengthPolicy::Bits), "38006F4529120");
+        assert_eq!(encode(TEST_INPUT1_3, LengthPolicy::Count), "EE00D40C82306");
+
+        // Large literals and operators with many sub-packets
+        let tree = Packet::new(2, PacketType::Sum(
+            (0..2100).map(|i| Packet::new(i as u8 % 8, PacketType::Literal(i % 16))).chain([Packet::new(7, PacketType::Literal(EvalNum::MAX))]).collect()
+        ));
+
+        let hex = tree.encode(LengthPolicy::Count).unwrap();
+        assert_eq!(parse_data(&load::load_buf(hex.as_bytes()).unwrap()), tree);
+
+        assert!(Packet::new(8, PacketType::Literal(1)).encode(LengthPolicy::Bits).is_err());
+        assert!(Packet::new(1, PacketType::Literal(-1)).encode(LengthPolicy::Bits).is_err());
+        assert!(LengthPolicy::parse("bytes").is_err());
+    }
+
     #[test]
     fn test2_1() {
         let data = load::load_buf(TEST_INPUT2_1.as_bytes()).unwrap();
@@ -0,0 +1,81 @@
+#[derive(Default)]
+pub struct BitWriter {
+    data: Vec<u8>,
+    cur_pos: usize
+}
+
+impl BitWriter {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get_pos(&self) -> usize {
+        self.cur_pos
+    }
+
+    pub fn put_bits<B>(&mut self, value: B, count: u8)
+        where B: Into<u64>
+    {
+        let pos = self.cur_pos;
+
+        self.cur_pos += count as usize;
+        self.data.resize(self.cur_pos.div_ceil(8), 0);
+
+        self.set_bits(pos, value, count);
+    }
+
+    pub fn set_bits<B>(&mut self, pos: usize, value: B, count: u8)
+        where B: Into<u64>
+    {
+        // Overwrite bits already written, most significant first
+        let value: u64 = value.into();
+
+        for bit in 0..count as usize {
+            let (byte, shift) = ((pos + bit) >> 3, 7 - ((pos + bit) & 0x07));
+            let set = (value >> (count as usize - bit - 1)) & 1;
+
+            self.data[byte] = (self.data[byte] & !(1 << shift)) | ((set as u8) << shift);
+        }
+    }
+
+    pub fn to_hex(&self) -> String {
+        // Pad with zeros to a whole number of hex digits
+        let hex: String = self.data.iter().map(|b| format!("{:02X}", b)).collect();
+
+        hex[..self.cur_pos.div_ceil(4)].to_string()
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::load;
+    use crate::parser::Parser;
+
+    #[test]
+    fn test_writer() {
+        let mut writer = BitWriter::new();
+
+        for count in 1..=11 {
+            writer.put_bits(0b10101010101u16 >> (11 - count), count);
+        }
+
+        assert_eq!(writer.get_pos(), 66);
+        assert_eq!(writer.to_hex(), "D6AB555AAAAD55554");
+
+        // Reads back with the parser
+        let data = load::load_buf(writer.to_hex().as_bytes()).unwrap();
+        let mut parser = Parser::new(&data);
+
+        for count in 1..=11 {
+            assert_eq!(parser.get_bits::<u16>(count), 0b10101010101u16 >> (11 - count));
+        }
+
+        // Patch earlier bits
+        writer.set_bits(1, 0b111u8, 3);
+        assert!(writer.to_hex().starts_with("F6"));
+    }
+
+}